@@ -0,0 +1,152 @@
+//! Runs the gameplay loop without a window, renderer or audio device.
+//!
+//! `cargo run --example headless -- 3600 42` simulates the given number of simulation ticks
+//! of a run seeded with the given seed
+//! while a naive autopilot keeps the boat in the middle of the river and fires straight ahead,
+//! then prints how far the boat got.
+//!
+//! Append `--record <file>` to save the inputs of the run
+//! or `--replay <file>` to play a recorded run back instead of using the autopilot.
+//! `--balance <file>` plays with a tweaked copy of `assets/game.balance.ron`,
+//! `--preset <name>` on another difficulty preset than normal
+//! and `--daily` plays today's daily challenge instead of the given seed.
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::utils::Instant;
+use grand_escape::actions::Actions;
+use grand_escape::balance::Balance;
+use grand_escape::daily::GameMode;
+use grand_escape::difficulty::DifficultyPreset;
+use grand_escape::environment::MAP_WIDTH;
+use grand_escape::health::Health;
+use grand_escape::player::{Movement, Player};
+use grand_escape::random::FixedSeed;
+use grand_escape::replay::{Playback, Recording, Replay};
+use grand_escape::score::GameScore;
+use grand_escape::stats::RunStats;
+use grand_escape::{GameState, SimulationPlugin};
+
+fn main() {
+    let ticks: u32 = std::env::args()
+        .nth(1)
+        .and_then(|ticks| ticks.parse().ok())
+        .unwrap_or(3600);
+    let seed: u64 = std::env::args()
+        .nth(2)
+        .and_then(|seed| seed.parse().ok())
+        .unwrap_or(42);
+    let record = arg_value("--record");
+    let replay = arg_value("--replay").map(|path| {
+        Replay::load(path.as_ref())
+            .unwrap_or_else(|error| panic!("Could not load replay {}: {}", path, error))
+    });
+    let autopilot = replay.is_none();
+    let daily = std::env::args().any(|arg| arg == "--daily");
+    let preset = arg_value("--preset").map(|name| {
+        DifficultyPreset::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(&name))
+            .unwrap_or_else(|| panic!("Unknown difficulty preset {}", name))
+    });
+    let balance: Option<Balance> = arg_value("--balance").map(|path| {
+        let balance = std::fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("Could not read balance {}: {}", path, error));
+        let balance: Balance = ron::from_str(&balance)
+            .unwrap_or_else(|error| panic!("Could not parse balance {}: {}", path, error));
+        balance
+            .validate()
+            .unwrap_or_else(|error| panic!("Invalid balance {}: {}", path, error));
+        balance
+    });
+
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugin(TransformPlugin)
+        .add_plugin(HierarchyPlugin)
+        .add_plugin(SimulationPlugin)
+        .insert_resource(FixedSeed((!daily).then_some(seed)))
+        .insert_resource(NextState(Some(GameState::Init)));
+    if let Some(replay) = replay {
+        app.insert_resource(Playback::new(replay));
+    }
+    if let Some(balance) = balance {
+        app.insert_resource(balance);
+    }
+    if let Some(preset) = preset {
+        app.insert_resource(preset);
+    }
+    if daily {
+        app.insert_resource(GameMode::Daily);
+    }
+
+    // Advance the clock by exactly one simulation tick per update, no matter how fast we run
+    let start = Instant::now();
+    let tick_duration = app.world.resource::<FixedTime>().period;
+    for tick in 0..ticks {
+        if app.world.resource::<State<GameState>>().0 == GameState::End {
+            println!("Boat sank after {} ticks", tick);
+            break;
+        }
+        if autopilot {
+            steer_boat(&mut app.world);
+        }
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(
+            start + tick_duration * tick,
+        ));
+        app.update();
+    }
+
+    if let Some(path) = record {
+        let recording = app.world.resource::<Recording>().replay();
+        recording
+            .save(path.as_ref())
+            .unwrap_or_else(|error| panic!("Could not save replay {}: {}", path, error));
+        println!("Recorded {} ticks to {}", recording.frames.len(), path);
+    }
+
+    let score = app.world.resource::<GameScore>();
+    println!(
+        "Score: {}, distance: {:.0}m, time: {:.1}s",
+        score.score,
+        score.distance_traveled,
+        score.elapsed_time.elapsed_secs()
+    );
+    let stats = app.world.resource::<RunStats>();
+    println!(
+        "Shots: {}, hits: {}, kills: {}, damage taken: {}",
+        stats.shots_fired,
+        stats.hits,
+        stats.side_cannons_destroyed + stats.pirates_destroyed,
+        stats.damage_taken.total()
+    );
+    if let Ok(health) = app
+        .world
+        .query_filtered::<&Health, With<Player>>()
+        .get_single(&app.world)
+    {
+        println!("Health: {}/{}", health.health_amount, health.max_health);
+    }
+}
+
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
+/// Keeps the boat in the middle of the river and fires straight ahead
+fn steer_boat(world: &mut World) {
+    let (player_translation, heading) = world
+        .query_filtered::<(&Transform, &Movement), With<Player>>()
+        .get_single(world)
+        .map(|(transform, movement)| (transform.translation.truncate(), movement.vector))
+        .unwrap_or_default();
+    let wished_heading = ((MAP_WIDTH / 2. - player_translation.x) / 200.).clamp(-0.5, 0.5);
+    let mut actions = world.resource_mut::<Actions>();
+    actions.player_movement = if (wished_heading - heading.x).abs() < 0.05 {
+        None
+    } else {
+        Some(Vec2::new((wished_heading - heading.x).signum(), 0.))
+    };
+    actions.player_aim = Some(player_translation + Vec2::new(0., 200.));
+    actions.player_fire = true;
+}
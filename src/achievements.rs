@@ -0,0 +1,400 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    difficulty::DifficultyPreset,
+    loading::FontAssets,
+    menu::{ButtonAction, ButtonColors},
+    replay::is_playing_back,
+    score::GameScore,
+    stats::RunStats,
+    storage, GameState,
+};
+
+pub struct AchievementsPlugin;
+
+/// This plugin unlocks achievements from the score and statistics of the run,
+/// pops up a toast for every new one and lists all of them on their own screen.
+/// Unlocks are saved right away and kept between sessions, replays that are played back never unlock anything
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Achievements::load())
+            .add_event::<AchievementUnlocked>()
+            .add_startup_system(setup_toasts)
+            .add_system(
+                unlock_achievements
+                    .run_if(not(is_playing_back))
+                    .in_set(OnUpdate(GameState::Playing)),
+            )
+            .add_system(
+                count_finished_run
+                    .run_if(not(is_playing_back))
+                    .in_schedule(OnEnter(GameState::End)),
+            )
+            .add_system(show_toasts)
+            .add_system(setup_achievements_screen.in_schedule(OnEnter(GameState::Achievements)))
+            .add_system(cleanup_achievements_screen.in_schedule(OnExit(GameState::Achievements)));
+    }
+}
+
+const STORAGE_NAME: &str = "achievements";
+
+/// How long the toast of a new achievement stays on screen
+const TOAST_DURATION: Duration = Duration::from_secs(3);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Achievement {
+    FirstBlood,
+    Voyager,
+    PirateHunter,
+    Sharpshooter,
+    Untouchable,
+    IronHull,
+    Nightmare,
+    Seafarer,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 8] = [
+        Achievement::FirstBlood,
+        Achievement::Voyager,
+        Achievement::PirateHunter,
+        Achievement::Sharpshooter,
+        Achievement::Untouchable,
+        Achievement::IronHull,
+        Achievement::Nightmare,
+        Achievement::Seafarer,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "First blood",
+            Achievement::Voyager => "Voyager",
+            Achievement::PirateHunter => "Pirate hunter",
+            Achievement::Sharpshooter => "Sharpshooter",
+            Achievement::Untouchable => "Untouchable",
+            Achievement::IronHull => "Iron hull",
+            Achievement::Nightmare => "Living nightmare",
+            Achievement::Seafarer => "Seafarer",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "Destroy a side cannon",
+            Achievement::Voyager => "Travel 1000m in one run",
+            Achievement::PirateHunter => "Destroy 10 pirates in one run",
+            Achievement::Sharpshooter => "Hit 3 of every 4 shots with at least 50 shots fired",
+            Achievement::Untouchable => "Travel 500m without taking any damage",
+            Achievement::IronHull => "Travel 500m on Hard or harder without a single repair",
+            Achievement::Nightmare => "Travel 300m on Nightmare",
+            Achievement::Seafarer => "Travel 10000m over all runs",
+        }
+    }
+
+    /// Whether the current run, with the runs before it, earned the achievement
+    fn is_reached(&self, game_score: &GameScore, stats: &RunStats, total_distance: f32) -> bool {
+        let distance = game_score.distance_traveled;
+        match self {
+            Achievement::FirstBlood => stats.side_cannons_destroyed >= 1,
+            Achievement::Voyager => distance >= 1000.,
+            Achievement::PirateHunter => stats.pirates_destroyed >= 10,
+            Achievement::Sharpshooter => {
+                stats.shots_fired >= 50 && stats.accuracy().is_some_and(|accuracy| accuracy >= 0.75)
+            }
+            Achievement::Untouchable => distance >= 500. && stats.damage_taken.total() == 0,
+            Achievement::IronHull => {
+                matches!(
+                    game_score.preset,
+                    DifficultyPreset::Hard | DifficultyPreset::Nightmare
+                ) && distance >= 500.
+                    && stats.repairs_collected == 0
+            }
+            Achievement::Nightmare => {
+                game_score.preset == DifficultyPreset::Nightmare && distance >= 300.
+            }
+            Achievement::Seafarer => total_distance + distance >= 10000.,
+        }
+    }
+
+    /// Progress towards achievements that take more than one run, as done out of needed
+    fn progress(&self, achievements: &Achievements) -> Option<(f32, f32)> {
+        match self {
+            Achievement::Seafarer => Some((achievements.total_distance.min(10000.), 10000.)),
+            _ => None,
+        }
+    }
+}
+
+/// Everything unlocked so far and what is counted over all runs
+#[derive(Resource, Serialize, Deserialize, Default, Debug)]
+pub struct Achievements {
+    pub unlocked: Vec<Achievement>,
+    /// Distance of all finished runs together
+    pub total_distance: f32,
+}
+
+impl Achievements {
+    fn load() -> Achievements {
+        let Some(content) = storage::read(STORAGE_NAME) else {
+            return Achievements::default();
+        };
+        ron::from_str(&content).unwrap_or_else(|error| {
+            warn!("Ignoring unreadable achievements: {}", error);
+            Achievements::default()
+        })
+    }
+
+    fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| storage::write(STORAGE_NAME, &content));
+        if let Err(error) = result {
+            error!("Could not save achievements: {}", error);
+        }
+    }
+
+    pub fn is_unlocked(&self, achievement: Achievement) -> bool {
+        self.unlocked.contains(&achievement)
+    }
+
+    /// Unlocks everything the run has earned since the last check, returns whether anything was
+    fn unlock_reached(
+        &mut self,
+        game_score: &GameScore,
+        stats: &RunStats,
+        unlocked_events: &mut EventWriter<AchievementUnlocked>,
+    ) -> bool {
+        let mut changed = false;
+        for achievement in Achievement::ALL {
+            if !self.is_unlocked(achievement)
+                && achievement.is_reached(game_score, stats, self.total_distance)
+            {
+                self.unlocked.push(achievement);
+                unlocked_events.send(AchievementUnlocked(achievement));
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Sent once when an achievement gets unlocked
+pub struct AchievementUnlocked(pub Achievement);
+
+fn unlock_achievements(
+    game_score: Res<GameScore>,
+    stats: Res<RunStats>,
+    mut achievements: ResMut<Achievements>,
+    mut unlocked_events: EventWriter<AchievementUnlocked>,
+) {
+    if achievements.unlock_reached(&game_score, &stats, &mut unlocked_events) {
+        achievements.save();
+    }
+}
+
+/// Adds the run to the totals once it is over and unlocks what it earned in its last moments
+fn count_finished_run(
+    game_score: Res<GameScore>,
+    stats: Res<RunStats>,
+    mut achievements: ResMut<Achievements>,
+    mut unlocked_events: EventWriter<AchievementUnlocked>,
+) {
+    achievements.unlock_reached(&game_score, &stats, &mut unlocked_events);
+    achievements.total_distance += game_score.distance_traveled;
+    achievements.save();
+}
+
+/// Column at the top of the screen the toasts are stacked in
+#[derive(Component)]
+struct ToastArea;
+
+#[derive(Component)]
+struct Toast(Timer);
+
+fn setup_toasts(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::width(Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                position: UiRect::top(Val::Px(60.)),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(ToastArea);
+}
+
+fn show_toasts(
+    mut commands: Commands,
+    mut unlocked_events: EventReader<AchievementUnlocked>,
+    toast_area_q: Query<Entity, With<ToastArea>>,
+    mut toasts_q: Query<(Entity, &mut Toast)>,
+    font_assets: Option<Res<FontAssets>>,
+    time: Res<Time>,
+) {
+    for (entity, mut toast) in toasts_q.iter_mut() {
+        if toast.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let Some(font_assets) = font_assets else {
+        return;
+    };
+    let toast_area = toast_area_q.single();
+    for AchievementUnlocked(achievement) in unlocked_events.iter() {
+        commands.entity(toast_area).with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::bottom(Val::Px(5.)),
+                        padding: UiRect::all(Val::Px(10.)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0.15, 0.15, 0.15, 0.9).into(),
+                    ..default()
+                })
+                .insert(Toast(Timer::new(TOAST_DURATION, TimerMode::Once)))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        format!("Achievement unlocked: {}", achievement.name()),
+                        TextStyle {
+                            font: font_assets.fira_sans.clone(),
+                            font_size: 20.0,
+                            color: Color::rgb(1., 0.8, 0.3),
+                        },
+                    ));
+                    parent.spawn(TextBundle::from_section(
+                        achievement.description(),
+                        TextStyle {
+                            font: font_assets.fira_sans_reg.clone(),
+                            font_size: 16.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                });
+        });
+    }
+}
+
+#[derive(Component)]
+struct AchievementsScreen;
+
+fn setup_achievements_screen(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    achievements: Res<Achievements>,
+) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(AchievementsScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!(
+                    "Achievements {}/{}",
+                    achievements.unlocked.len(),
+                    Achievement::ALL.len()
+                ),
+                TextStyle {
+                    font: font_assets.fira_sans.clone(),
+                    font_size: 50.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+            for achievement in Achievement::ALL {
+                let unlocked = achievements.is_unlocked(achievement);
+                let description = match achievement.progress(&achievements) {
+                    Some((done, needed)) if !unlocked => {
+                        format!("{} ({:.0}/{:.0})", achievement.description(), done, needed)
+                    }
+                    _ => achievement.description().to_string(),
+                };
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::width(Val::Px(500.)),
+                            flex_direction: FlexDirection::Column,
+                            margin: UiRect::top(Val::Px(8.)),
+                            padding: UiRect::all(Val::Px(8.)),
+                            ..default()
+                        },
+                        background_color: if unlocked {
+                            Color::rgb(0.45, 0.3, 0.1)
+                        } else {
+                            Color::rgb(0.15, 0.15, 0.15)
+                        }
+                        .into(),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            if unlocked {
+                                achievement.name().to_string()
+                            } else {
+                                format!("{} (locked)", achievement.name())
+                            },
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 20.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                        parent.spawn(TextBundle::from_section(
+                            description,
+                            TextStyle {
+                                font: font_assets.fira_sans_reg.clone(),
+                                font_size: 16.0,
+                                color: Color::rgb(0.8, 0.8, 0.8),
+                            },
+                        ));
+                    });
+            }
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(20.)),
+                        padding: UiRect::all(Val::Px(10.)),
+                        ..default()
+                    },
+                    background_color: button_colors.normal.into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Back",
+                        TextStyle {
+                            font: font_assets.fira_sans_reg.clone(),
+                            font_size: 24.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+                })
+                .insert(ButtonAction::BackButton);
+        });
+}
+
+fn cleanup_achievements_screen(
+    mut commands: Commands,
+    screen: Query<Entity, With<AchievementsScreen>>,
+) {
+    commands.entity(screen.single()).despawn_recursive();
+}
@@ -1,35 +1,358 @@
-use bevy::prelude::{Input, KeyCode, Res};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::{Deserialize, Serialize};
 
+use crate::storage;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum GameControl {
-    // Up,
-    // Down,
+    Up,
+    Down,
     Left,
     Right,
+    AimLeft,
+    AimRight,
+    Fire,
+    AltFire,
+    Pause,
 }
 
 impl GameControl {
-    pub fn pressed(&self, keyboard_input: &Res<Input<KeyCode>>) -> bool {
+    pub const ALL: [GameControl; 9] = [
+        GameControl::Up,
+        GameControl::Down,
+        GameControl::Left,
+        GameControl::Right,
+        GameControl::AimLeft,
+        GameControl::AimRight,
+        GameControl::Fire,
+        GameControl::AltFire,
+        GameControl::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameControl::Up => "Speed up",
+            GameControl::Down => "Slow down",
+            GameControl::Left => "Steer left",
+            GameControl::Right => "Steer right",
+            GameControl::AimLeft => "Aim left",
+            GameControl::AimRight => "Aim right",
+            GameControl::Fire => "Fire",
+            GameControl::AltFire => "Spread shot",
+            GameControl::Pause => "Pause",
+        }
+    }
+
+    fn default_bindings(&self) -> [Option<Binding>; BINDING_SLOTS] {
         match self {
-            // GameControl::Up => {
-            //     keyboard_input.pressed(KeyCode::W) || keyboard_input.pressed(KeyCode::Up)
-            // }
-            // GameControl::Down => {
-            //     keyboard_input.pressed(KeyCode::S) || keyboard_input.pressed(KeyCode::Down)
-            // }
-            GameControl::Left => {
-                keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left)
+            GameControl::Up => [
+                Some(Binding::Key(KeyCode::W)),
+                Some(Binding::Key(KeyCode::Up)),
+            ],
+            GameControl::Down => [
+                Some(Binding::Key(KeyCode::S)),
+                Some(Binding::Key(KeyCode::Down)),
+            ],
+            GameControl::Left => [
+                Some(Binding::Key(KeyCode::A)),
+                Some(Binding::Key(KeyCode::Left)),
+            ],
+            GameControl::Right => [
+                Some(Binding::Key(KeyCode::D)),
+                Some(Binding::Key(KeyCode::Right)),
+            ],
+            GameControl::AimLeft => [Some(Binding::Key(KeyCode::Q)), None],
+            GameControl::AimRight => [Some(Binding::Key(KeyCode::E)), None],
+            GameControl::Fire => [
+                Some(Binding::Mouse(MouseButton::Left)),
+                Some(Binding::Key(KeyCode::Space)),
+            ],
+            GameControl::AltFire => [
+                Some(Binding::Mouse(MouseButton::Right)),
+                Some(Binding::Key(KeyCode::F)),
+            ],
+            GameControl::Pause => [Some(Binding::Key(KeyCode::Escape)), None],
+        }
+    }
+}
+
+/// How many keys or buttons each control can be bound to
+pub const BINDING_SLOTS: usize = 2;
+
+/// A key or mouse button a control is bound to
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+impl Binding {
+    pub fn name(&self) -> String {
+        match self {
+            Binding::Key(key) => format!("{:?}", key),
+            Binding::Mouse(button) => format!("{:?} mouse", button),
+        }
+    }
+
+    fn pressed(&self, keyboard_input: &Input<KeyCode>, mouse_input: &Input<MouseButton>) -> bool {
+        match self {
+            Binding::Key(key) => keyboard_input.pressed(*key),
+            Binding::Mouse(button) => mouse_input.pressed(*button),
+        }
+    }
+
+    fn just_pressed(
+        &self,
+        keyboard_input: &Input<KeyCode>,
+        mouse_input: &Input<MouseButton>,
+    ) -> bool {
+        match self {
+            Binding::Key(key) => keyboard_input.just_pressed(*key),
+            Binding::Mouse(button) => mouse_input.just_pressed(*button),
+        }
+    }
+}
+
+/// How the player cannon is aimed when the gamepad's right stick is not used
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum AimMode {
+    /// At the mouse cursor
+    #[default]
+    Mouse,
+    /// The aim keys turn the cannon relative to the boat
+    Keys,
+    /// At the nearest threat, the aim keys turn the cannon while there is none
+    LockOn,
+}
+
+impl AimMode {
+    pub const ALL: [AimMode; 3] = [AimMode::Mouse, AimMode::Keys, AimMode::LockOn];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AimMode::Mouse => "Mouse",
+            AimMode::Keys => "Keys",
+            AimMode::LockOn => "Lock on",
+        }
+    }
+
+    pub fn next(&self) -> AimMode {
+        let index = AimMode::ALL
+            .iter()
+            .position(|mode| mode == self)
+            .unwrap_or(0);
+        AimMode::ALL[(index + 1) % AimMode::ALL.len()]
+    }
+}
+
+const STORAGE_NAME: &str = "controls";
+
+/// Which keys and mouse buttons trigger each control, rebound on the controls screen.
+/// Controls missing from the saved map, like ones added in a later version, get their default bindings
+/// unless those are already taken
+#[derive(Resource, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: HashMap<GameControl, [Option<Binding>; BINDING_SLOTS]>,
+    #[serde(default)]
+    pub aim_mode: AimMode,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        InputMap {
+            bindings: GameControl::ALL
+                .into_iter()
+                .map(|control| (control, control.default_bindings()))
+                .collect(),
+            aim_mode: AimMode::default(),
+        }
+    }
+}
+
+impl InputMap {
+    pub fn load() -> InputMap {
+        let mut input_map = storage::read(STORAGE_NAME)
+            .map(|content| {
+                ron::from_str::<InputMap>(&content).unwrap_or_else(|error| {
+                    warn!("Ignoring unreadable controls: {}", error);
+                    InputMap::default()
+                })
+            })
+            .unwrap_or_default();
+        for control in GameControl::ALL {
+            if !input_map.bindings.contains_key(&control) {
+                // A default key the player already uses for something else is left out
+                let bindings = control.default_bindings().map(|binding| {
+                    binding.filter(|binding| input_map.bound_to(*binding).is_none())
+                });
+                input_map.bindings.insert(control, bindings);
             }
-            GameControl::Right => {
-                keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right)
+        }
+        input_map
+    }
+
+    pub fn save(&self) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| storage::write(STORAGE_NAME, &content));
+        if let Err(error) = result {
+            error!("Could not save controls: {}", error);
+        }
+    }
+
+    pub fn bindings(&self, control: GameControl) -> [Option<Binding>; BINDING_SLOTS] {
+        self.bindings.get(&control).copied().unwrap_or_default()
+    }
+
+    /// Everything the control is bound to, for telling the player what to press
+    pub fn describe(&self, control: GameControl) -> String {
+        let names: Vec<String> = self
+            .bindings(control)
+            .iter()
+            .flatten()
+            .map(Binding::name)
+            .collect();
+        if names.is_empty() {
+            "(unbound)".to_string()
+        } else {
+            names.join("/")
+        }
+    }
+
+    /// The control and slot the binding is already used by
+    pub fn bound_to(&self, binding: Binding) -> Option<(GameControl, usize)> {
+        GameControl::ALL.into_iter().find_map(|control| {
+            self.bindings(control)
+                .iter()
+                .position(|bound| *bound == Some(binding))
+                .map(|slot| (control, slot))
+        })
+    }
+
+    /// Binds a slot of the control, or clears it with none.
+    /// Fails with the control that already uses the binding, a key or button only triggers one control
+    pub fn bind(
+        &mut self,
+        control: GameControl,
+        slot: usize,
+        binding: Option<Binding>,
+    ) -> Result<(), GameControl> {
+        if let Some((bound_control, bound_slot)) =
+            binding.and_then(|binding| self.bound_to(binding))
+        {
+            if (bound_control, bound_slot) != (control, slot) {
+                return Err(bound_control);
             }
         }
+        self.bindings
+            .entry(control)
+            .or_insert_with(|| control.default_bindings())[slot] = binding;
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        *self = InputMap::default();
+    }
+
+    pub fn pressed(
+        &self,
+        control: GameControl,
+        keyboard_input: &Input<KeyCode>,
+        mouse_input: &Input<MouseButton>,
+    ) -> bool {
+        self.bindings(control)
+            .iter()
+            .flatten()
+            .any(|binding| binding.pressed(keyboard_input, mouse_input))
+    }
+
+    pub fn just_pressed(
+        &self,
+        control: GameControl,
+        keyboard_input: &Input<KeyCode>,
+        mouse_input: &Input<MouseButton>,
+    ) -> bool {
+        self.bindings(control)
+            .iter()
+            .flatten()
+            .any(|binding| binding.just_pressed(keyboard_input, mouse_input))
     }
 }
 
-pub fn get_movement(control: GameControl, input: &Res<Input<KeyCode>>) -> f32 {
-    if control.pressed(input) {
+pub fn get_movement(
+    control: GameControl,
+    input_map: &InputMap,
+    keyboard_input: &Input<KeyCode>,
+    mouse_input: &Input<MouseButton>,
+) -> f32 {
+    if input_map.pressed(control, keyboard_input, mouse_input) {
         1.0
     } else {
         0.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_rejects_a_binding_used_by_another_control() {
+        let mut input_map = InputMap::default();
+        assert_eq!(
+            input_map.bind(GameControl::Fire, 1, Some(Binding::Key(KeyCode::W))),
+            Err(GameControl::Up)
+        );
+        assert_eq!(
+            input_map.bindings(GameControl::Fire)[1],
+            Some(Binding::Key(KeyCode::Space))
+        );
+        assert_eq!(
+            input_map.bound_to(Binding::Key(KeyCode::W)),
+            Some((GameControl::Up, 0))
+        );
+    }
+
+    #[test]
+    fn bind_rejects_the_other_slot_of_the_same_control() {
+        let mut input_map = InputMap::default();
+        assert_eq!(
+            input_map.bind(GameControl::Up, 1, Some(Binding::Key(KeyCode::W))),
+            Err(GameControl::Up)
+        );
+    }
+
+    #[test]
+    fn bind_accepts_the_binding_already_in_the_slot() {
+        let mut input_map = InputMap::default();
+        assert_eq!(
+            input_map.bind(GameControl::Up, 0, Some(Binding::Key(KeyCode::W))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn binding_is_free_again_after_clearing_its_slot() {
+        let mut input_map = InputMap::default();
+        assert_eq!(input_map.bind(GameControl::Up, 0, None), Ok(()));
+        assert_eq!(input_map.bound_to(Binding::Key(KeyCode::W)), None);
+        assert_eq!(
+            input_map.bind(GameControl::Fire, 1, Some(Binding::Key(KeyCode::W))),
+            Ok(())
+        );
+        assert_eq!(
+            input_map.bound_to(Binding::Key(KeyCode::W)),
+            Some((GameControl::Fire, 1))
+        );
+    }
+
+    #[test]
+    fn describe_names_every_binding() {
+        let mut input_map = InputMap::default();
+        assert_eq!(input_map.describe(GameControl::Up), "W/Up");
+        assert_eq!(input_map.describe(GameControl::Fire), "Left mouse/Space");
+        input_map.bind(GameControl::Pause, 0, None).unwrap();
+        assert_eq!(input_map.describe(GameControl::Pause), "(unbound)");
+    }
+}
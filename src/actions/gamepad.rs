@@ -0,0 +1,79 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::prelude::*;
+
+/// The gamepad the player uses, the first one that got connected.
+/// When it is unplugged another connected gamepad takes over, if there is one
+#[derive(Resource, Default)]
+pub struct ActiveGamepad(pub Option<Gamepad>);
+
+/// The [`ActiveGamepad`] was unplugged, other gamepads coming and going do not send this
+pub struct ActiveGamepadLost;
+
+impl ActiveGamepad {
+    pub fn pressed(&self, buttons: &Input<GamepadButton>, button_type: GamepadButtonType) -> bool {
+        self.0
+            .is_some_and(|gamepad| buttons.pressed(GamepadButton::new(gamepad, button_type)))
+    }
+
+    pub fn just_pressed(
+        &self,
+        buttons: &Input<GamepadButton>,
+        button_type: GamepadButtonType,
+    ) -> bool {
+        self.0
+            .is_some_and(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+    }
+
+    /// Position of the given stick, zero inside its dead zone or without a gamepad
+    pub fn stick(
+        &self,
+        axes: &Axis<GamepadAxis>,
+        x_type: GamepadAxisType,
+        y_type: GamepadAxisType,
+    ) -> Vec2 {
+        let Some(gamepad) = self.0 else {
+            return Vec2::ZERO;
+        };
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default()
+        };
+        Vec2::new(axis(x_type), axis(y_type))
+    }
+
+    pub fn left_stick(&self, axes: &Axis<GamepadAxis>) -> Vec2 {
+        self.stick(
+            axes,
+            GamepadAxisType::LeftStickX,
+            GamepadAxisType::LeftStickY,
+        )
+    }
+
+    pub fn right_stick(&self, axes: &Axis<GamepadAxis>) -> Vec2 {
+        self.stick(
+            axes,
+            GamepadAxisType::RightStickX,
+            GamepadAxisType::RightStickY,
+        )
+    }
+}
+
+pub fn track_gamepads(
+    mut connection_events: EventReader<GamepadConnectionEvent>,
+    gamepads: Res<Gamepads>,
+    mut active_gamepad: ResMut<ActiveGamepad>,
+    mut lost_events: EventWriter<ActiveGamepadLost>,
+) {
+    for event in connection_events.iter() {
+        if event.connected() {
+            info!("Gamepad {} connected", event.gamepad.id);
+            active_gamepad.0.get_or_insert(event.gamepad);
+        } else {
+            info!("Gamepad {} disconnected", event.gamepad.id);
+            if active_gamepad.0 == Some(event.gamepad) {
+                active_gamepad.0 = gamepads.iter().find(|gamepad| *gamepad != event.gamepad);
+                lost_events.send(ActiveGamepadLost);
+            }
+        }
+    }
+}
@@ -1,37 +1,247 @@
+use bevy::input::InputSystem;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 
-use crate::actions::game_control::{get_movement, GameControl};
+use crate::actions::game_control::get_movement;
+use crate::actions::gamepad::track_gamepads;
+use crate::actions::touch::track_touches;
+use crate::camera::MainCamera;
+use crate::enemy::{Enemy, EnemyPirate};
+use crate::health::Health;
+use crate::player::Player;
+use crate::replay::is_playing_back;
 use crate::GameState;
 
+pub use game_control::{AimMode, Binding, GameControl, InputMap, BINDING_SLOTS};
+pub use gamepad::{ActiveGamepad, ActiveGamepadLost};
+pub use touch::{TouchButton, TouchControls};
+
 mod game_control;
+mod gamepad;
+mod touch;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
+// This plugin listens for keyboard, mouse, gamepad and touch input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
+// The simulation only ever reads Actions, so a headless app can drive the player by writing them.
+// While a replay is played back the recorded Actions are used and live input is ignored.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<Actions>()
-            .add_system(set_movement_actions.in_set(OnUpdate(GameState::Playing)));
+            .insert_resource(InputMap::load())
+            .init_resource::<ActiveGamepad>()
+            .add_event::<ActiveGamepadLost>()
+            .init_resource::<TouchControls>()
+            .add_system(track_gamepads.in_base_set(CoreSet::PreUpdate))
+            .add_system(
+                track_touches
+                    .after(InputSystem)
+                    .in_base_set(CoreSet::PreUpdate),
+            )
+            .add_systems(
+                (
+                    set_movement_actions.run_if(not(is_playing_back)),
+                    set_aim_actions.run_if(not(is_playing_back)),
+                    set_fire_actions.run_if(not(is_playing_back)),
+                )
+                    .in_set(OnUpdate(GameState::Playing)),
+            );
     }
 }
 
 #[derive(Default, Resource)]
 pub struct Actions {
+    /// Steering in x, from -1 left to 1 right, and throttle in y, from -1 slowing down to 1 speeding up
     pub player_movement: Option<Vec2>,
+    /// World position the player cannon should aim at
+    pub player_aim: Option<Vec2>,
+    /// Direction in the world the player cannon should point to when there is no aim position
+    pub player_aim_direction: Option<Vec2>,
+    /// How fast to turn the player cannon relative to the boat when there is neither an aim position nor direction,
+    /// from -1 turning left to 1 turning right
+    pub player_aim_turn: f32,
+    /// Fire a single bullet once the cannon is loaded
+    pub player_fire: bool,
+    /// Fire a spread shot once the cannon is loaded, a single bullet is preferred when both are wanted
+    pub player_alt_fire: bool,
 }
 
-pub fn set_movement_actions(mut actions: ResMut<Actions>, keyboard_input: Res<Input<KeyCode>>) {
+/// Threats further away from the boat than this are not locked on to
+const LOCK_ON_RANGE: f32 = 400.;
+
+pub fn set_movement_actions(
+    mut actions: ResMut<Actions>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    touch_controls: Res<TouchControls>,
+) {
+    let d_pad = |button_type| {
+        if gamepad.pressed(&gamepad_buttons, button_type) {
+            1.0
+        } else {
+            0.0
+        }
+    };
+    // Pushing the stick only halfway up or down already throttles, so steering does not
+    let stick = gamepad.left_stick(&gamepad_axes);
+    let stick_throttle = if stick.y.abs() >= 0.5 {
+        stick.y.signum()
+    } else {
+        0.0
+    };
+    let touch_button = |button| {
+        if touch_controls.pressed(button) {
+            1.0
+        } else {
+            0.0
+        }
+    };
     let player_movement = Vec2::new(
-        get_movement(GameControl::Right, &keyboard_input)
-            - get_movement(GameControl::Left, &keyboard_input),
-        0.0, // get_movement(GameControl::Up, &keyboard_input)
-             //     - get_movement(GameControl::Down, &keyboard_input),
+        get_movement(
+            GameControl::Right,
+            &input_map,
+            &keyboard_input,
+            &mouse_input,
+        ) - get_movement(GameControl::Left, &input_map, &keyboard_input, &mouse_input)
+            + d_pad(GamepadButtonType::DPadRight)
+            - d_pad(GamepadButtonType::DPadLeft)
+            + stick.x
+            + touch_button(TouchButton::SteerRight)
+            - touch_button(TouchButton::SteerLeft),
+        get_movement(GameControl::Up, &input_map, &keyboard_input, &mouse_input)
+            - get_movement(GameControl::Down, &input_map, &keyboard_input, &mouse_input)
+            + d_pad(GamepadButtonType::DPadUp)
+            - d_pad(GamepadButtonType::DPadDown)
+            + stick_throttle
+            + touch_button(TouchButton::SpeedUp)
+            - touch_button(TouchButton::SlowDown),
     );
 
     if player_movement != Vec2::ZERO {
-        actions.player_movement = Some(player_movement.normalize());
+        // The stick steers gently when it is only pushed a little
+        actions.player_movement = Some(player_movement.clamp(Vec2::NEG_ONE, Vec2::ONE));
     } else {
         actions.player_movement = None;
     }
 }
+
+pub fn set_aim_actions(
+    mut actions: ResMut<Actions>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    player_q: Query<&Transform, With<Player>>,
+    threats_q: Query<(&Transform, &Health), Or<(With<Enemy>, With<EnemyPirate>)>>,
+    gamepad: Res<ActiveGamepad>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    touch_controls: Res<TouchControls>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    mut stick_direction: Local<Option<Vec2>>,
+) {
+    actions.player_aim_direction = None;
+    actions.player_aim_turn = 0.;
+
+    // A finger on the water aims right where it is
+    if let Some(touch_position) = touch_controls.aim() {
+        let window = window.get_single().unwrap();
+        let (camera, camera_transform) = camera_q.single();
+        // Touches are measured from the top of the window, the cursor from the bottom
+        let position = Vec2::new(touch_position.x, window.height() - touch_position.y);
+        actions.player_aim = camera
+            .viewport_to_world(camera_transform, position)
+            .map(|ray| ray.origin.truncate());
+        *stick_direction = None;
+        return;
+    }
+
+    // The right stick takes over while it is pushed
+    let stick = gamepad.right_stick(&gamepad_axes);
+    if stick != Vec2::ZERO {
+        *stick_direction = Some(stick.normalize());
+    } else if input_map.aim_mode != AimMode::Mouse || cursor_moved_events.iter().last().is_some() {
+        // When aiming with the mouse the cannon keeps pointing where the stick left it
+        // instead of swinging to wherever the cursor was, until the mouse is moved
+        *stick_direction = None;
+    }
+    if stick_direction.is_some() {
+        actions.player_aim = None;
+        actions.player_aim_direction = *stick_direction;
+        return;
+    }
+
+    let aim_turn = get_movement(
+        GameControl::AimRight,
+        &input_map,
+        &keyboard_input,
+        &mouse_input,
+    ) - get_movement(
+        GameControl::AimLeft,
+        &input_map,
+        &keyboard_input,
+        &mouse_input,
+    );
+    match input_map.aim_mode {
+        AimMode::Mouse => {
+            let window = window.get_single().unwrap();
+            let (camera, camera_transform) = camera_q.single();
+
+            // check if the cursor is inside the window and get its position
+            // then, ask bevy to convert into world coordinates, and truncate to discard Z
+            actions.player_aim = window
+                .cursor_position()
+                .and_then(|pos| camera.viewport_to_world(camera_transform, pos))
+                .map(|ray| ray.origin.truncate());
+        }
+        AimMode::Keys => {
+            actions.player_aim = None;
+            actions.player_aim_turn = aim_turn;
+        }
+        AimMode::LockOn => {
+            let player_position = player_q
+                .get_single()
+                .map(|transform| transform.translation.truncate())
+                .ok();
+            actions.player_aim = player_position.and_then(|player_position| {
+                threats_q
+                    .iter()
+                    .filter(|(_, health)| health.health_amount > 0)
+                    .map(|(transform, _)| transform.translation.truncate())
+                    .filter(|position| position.distance(player_position) <= LOCK_ON_RANGE)
+                    .min_by(|a, b| {
+                        a.distance_squared(player_position)
+                            .total_cmp(&b.distance_squared(player_position))
+                    })
+            });
+            if actions.player_aim.is_none() {
+                actions.player_aim_turn = aim_turn;
+            }
+        }
+    }
+}
+
+pub fn set_fire_actions(
+    mut actions: ResMut<Actions>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touch_controls: Res<TouchControls>,
+) {
+    actions.player_fire = input_map.pressed(GameControl::Fire, &keyboard_input, &mouse_input)
+        || gamepad.pressed(&gamepad_buttons, GamepadButtonType::RightTrigger2)
+        || gamepad.pressed(&gamepad_buttons, GamepadButtonType::RightTrigger)
+        || touch_controls.aim().is_some();
+    actions.player_alt_fire =
+        input_map.pressed(GameControl::AltFire, &keyboard_input, &mouse_input)
+            || gamepad.pressed(&gamepad_buttons, GamepadButtonType::LeftTrigger2)
+            || gamepad.pressed(&gamepad_buttons, GamepadButtonType::LeftTrigger)
+            || touch_controls.pressed(TouchButton::SpreadShot);
+}
@@ -0,0 +1,96 @@
+use bevy::input::touch::TouchInput;
+use bevy::prelude::*;
+
+/// On-screen button of the touch controls
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TouchButton {
+    SteerLeft,
+    SteerRight,
+    SpeedUp,
+    SlowDown,
+    SpreadShot,
+    Pause,
+}
+
+impl TouchButton {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TouchButton::SteerLeft => "Left",
+            TouchButton::SteerRight => "Right",
+            TouchButton::SpeedUp => "Faster",
+            TouchButton::SlowDown => "Slower",
+            TouchButton::SpreadShot => "Spread",
+            TouchButton::Pause => "Pause",
+        }
+    }
+}
+
+/// What the fingers on the screen are doing.
+/// The touch controls are only used and shown once the screen got touched.
+/// Fingers put down on a button press the buttons they are over, any other finger aims at where it is and fires
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    pub enabled: bool,
+    pressed: Vec<TouchButton>,
+    just_pressed: Vec<TouchButton>,
+    /// Window position of the finger aiming, from the top left corner
+    aim: Option<Vec2>,
+}
+
+impl TouchControls {
+    pub fn pressed(&self, button: TouchButton) -> bool {
+        self.pressed.contains(&button)
+    }
+
+    pub fn just_pressed(&self, button: TouchButton) -> bool {
+        self.just_pressed.contains(&button)
+    }
+
+    pub fn aim(&self) -> Option<Vec2> {
+        self.aim
+    }
+}
+
+pub fn track_touches(
+    mut touch_events: EventReader<TouchInput>,
+    touches: Res<Touches>,
+    buttons_q: Query<(&TouchButton, &Node, &GlobalTransform, &ComputedVisibility)>,
+    mut touch_controls: ResMut<TouchControls>,
+) {
+    if touch_events.iter().last().is_some() && !touch_controls.enabled {
+        info!("Touch input detected, showing touch controls");
+        touch_controls.enabled = true;
+    }
+    if !touch_controls.enabled {
+        return;
+    }
+
+    // UI nodes are positioned from the top left corner of the window, just like touches
+    let button_at = |position: Vec2| {
+        buttons_q
+            .iter()
+            .filter(|(_, _, _, visibility)| visibility.is_visible())
+            .find(|(_, node, transform, _)| {
+                let offset = (position - transform.translation().truncate()).abs();
+                offset.cmple(node.size() / 2.).all()
+            })
+            .map(|(button, ..)| *button)
+    };
+    let mut pressed = vec![];
+    let mut aim = None;
+    for touch in touches.iter() {
+        if button_at(touch.start_position()).is_some() {
+            // Sliding over to the next button presses that one instead
+            pressed.extend(button_at(touch.position()));
+        } else if aim.is_none() {
+            aim = Some(touch.position());
+        }
+    }
+    touch_controls.just_pressed = pressed
+        .iter()
+        .filter(|button| !touch_controls.pressed.contains(button))
+        .copied()
+        .collect();
+    touch_controls.pressed = pressed;
+    touch_controls.aim = aim;
+}
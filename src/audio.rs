@@ -1,5 +1,7 @@
 use crate::actions::{set_movement_actions, Actions};
+use crate::balance::Balance;
 use crate::loading::AudioAssets;
+use crate::player::{Movement, Player};
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_kira_audio::prelude::*;
@@ -10,8 +12,10 @@ pub struct InternalAudioPlugin;
 impl Plugin for InternalAudioPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(AudioPlugin)
+            .add_event::<SoundEvent>()
             .add_audio_channel::<WaterAudioChannel>()
             .add_system(start_audio.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(play_sound_effects.run_if(resource_exists::<AudioAssets>()))
             .add_system(
                 control_water_sound
                     .after(set_movement_actions)
@@ -20,12 +24,30 @@ impl Plugin for InternalAudioPlugin {
     }
 }
 
+/// Sound cues sent by the simulation.
+/// The simulation only tells what happened, this plugin decides how it sounds.
+pub enum SoundEvent {
+    PlayerShot,
+    EnemyShot,
+    BulletHitWood,
+    BulletHitRock,
+    ObstacleBroken,
+    PlayerCrashed,
+    EnemyCrashed,
+    Repair,
+    WeaponPowerUp,
+    WeaponPowerUpExhausted,
+}
+
 #[derive(Resource)]
 struct WaterAudioChannel;
 
 #[derive(Resource)]
 struct WaterAudio(Handle<AudioInstance>);
 
+/// Volume of the water at the speed runs start at, it gets louder the faster the boat goes
+const WATER_VOLUME: f64 = 0.3;
+
 fn start_audio(
     mut commands: Commands,
     audio_assets: Res<AudioAssets>,
@@ -35,27 +57,55 @@ fn start_audio(
     let handle = audio
         .play(audio_assets.water.clone())
         .looped()
-        .with_volume(0.3)
+        .with_volume(WATER_VOLUME)
         .handle();
     commands.insert_resource(WaterAudio(handle));
 }
 
+fn play_sound_effects(
+    mut sound_events: EventReader<SoundEvent>,
+    audio: Res<Audio>,
+    audio_assets: Res<AudioAssets>,
+) {
+    for sound in sound_events.iter() {
+        let (source, volume) = match sound {
+            SoundEvent::PlayerShot => (&audio_assets.bullet_fire, 0.7),
+            SoundEvent::EnemyShot => (&audio_assets.bullet_fire, 0.3),
+            SoundEvent::BulletHitWood => (&audio_assets.bullet_hit, 0.4),
+            SoundEvent::BulletHitRock => (&audio_assets.bullet_hit_rock, 0.8),
+            SoundEvent::ObstacleBroken => (&audio_assets.wood_break, 0.3),
+            SoundEvent::PlayerCrashed => (&audio_assets.boat_crash, 0.5),
+            SoundEvent::EnemyCrashed => (&audio_assets.boat_crash, 0.1),
+            SoundEvent::Repair => (&audio_assets.repair, 0.4),
+            SoundEvent::WeaponPowerUp => (&audio_assets.power_up_weapon, 0.7),
+            SoundEvent::WeaponPowerUpExhausted => (&audio_assets.power_up_weapon_exhaust, 0.7),
+        };
+        audio.play(source.clone()).with_volume(volume);
+    }
+}
+
 fn control_water_sound(
     actions: Res<Actions>,
     audio: Res<WaterAudio>,
+    balance: Res<Balance>,
+    player_q: Query<&Movement, With<Player>>,
     mut audio_instances: ResMut<Assets<AudioInstance>>,
+    mut volume: Local<f64>,
 ) {
     if let Some(instance) = audio_instances.get_mut(&audio.0) {
+        if let Ok(movement) = player_q.get_single() {
+            let next_volume = WATER_VOLUME * (movement.speed / balance.player.speed) as f64;
+            if (next_volume - *volume).abs() > 0.01 {
+                *volume = next_volume;
+                instance.set_volume(next_volume, AudioTween::default());
+            }
+        }
         match instance.state() {
-            PlaybackState::Paused { .. } => {
-                if actions.player_movement.is_some() {
-                    instance.resume(AudioTween::default());
-                }
+            PlaybackState::Paused { .. } if actions.player_movement.is_some() => {
+                instance.resume(AudioTween::default());
             }
-            PlaybackState::Playing { .. } => {
-                if actions.player_movement.is_none() {
-                    instance.pause(AudioTween::default());
-                }
+            PlaybackState::Playing { .. } if actions.player_movement.is_none() => {
+                instance.pause(AudioTween::default());
             }
             _ => {}
         }
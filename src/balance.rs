@@ -0,0 +1,358 @@
+use std::ops::Range;
+
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::Deserialize;
+
+use crate::collision::Shape;
+use crate::difficulty::{Difficulty, DifficultyPreset};
+use crate::health::Mass;
+use crate::spawn::SpawnKind;
+
+/// The balance every build starts with, the same file is loaded (and watched) as an asset later
+const DEFAULT_BALANCE: &str = include_str!("../assets/game.balance.ron");
+
+pub struct BalancePlugin;
+
+/// This plugin loads the game balance from `assets/game.balance.ron`
+/// and applies every change of that file to the running game.
+/// A file that does not parse or fails [`Balance::validate`] is not applied, the previous balance stays.
+/// The simulation itself only reads the [`Balance`] resource, which starts out with the bundled defaults
+impl Plugin for BalancePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Balance>()
+            .init_asset_loader::<BalanceLoader>()
+            .add_system(apply_balance);
+    }
+}
+
+/// Tunable numbers of the game, see `assets/game.balance.ron` for what they mean
+#[derive(Resource, Deserialize, TypeUuid, Clone, Debug)]
+#[uuid = "4c27d656-2163-4594-b115-9068d21a07fd"]
+pub struct Balance {
+    pub player: PlayerBalance,
+    pub obstacles: ObstacleBalance,
+    pub river: RiverBalance,
+    pub side_cannons: SideCannonBalance,
+    pub pirates: PirateBalance,
+    pub power_ups: PowerUpBalance,
+    pub difficulty: DifficultyBalance,
+    pub spawns: SpawnBalance,
+    pub presets: PresetsBalance,
+}
+
+impl Default for Balance {
+    fn default() -> Self {
+        ron::from_str(DEFAULT_BALANCE).expect("bundled game balance is valid")
+    }
+}
+
+impl Balance {
+    /// Catches numbers the game can not run with, a file with any of them is not applied
+    pub fn validate(&self) -> Result<(), String> {
+        let player = &self.player;
+        if player.speed <= 0. {
+            return Err("player speed has to be greater than zero".to_string());
+        }
+        if player.min_speed > player.max_speed {
+            return Err("player min speed can not be above max speed".to_string());
+        }
+        if self.obstacles.variants.is_empty() {
+            return Err("there has to be at least one obstacle variant".to_string());
+        }
+        if let Some(index) = self
+            .spawns
+            .waves
+            .iter()
+            .position(|wave| wave.interval_ms.is_empty())
+        {
+            return Err(format!("spawn wave {} has an empty interval", index));
+        }
+        let difficulty = &self.difficulty;
+        if difficulty.distance_per_point <= 0
+            || difficulty.seconds_per_point <= 0.
+            || difficulty.points_per_level <= 0.
+        {
+            return Err("difficulty points have to be greater than zero".to_string());
+        }
+        if let Some(index) = self
+            .spawns
+            .waves
+            .iter()
+            .position(|wave| wave.first_delay_secs < 0.)
+        {
+            return Err(format!("spawn wave {} has a negative first delay", index));
+        }
+        // Growth scales at least by one as long as levels can not go below zero
+        if difficulty.spawn_rate_growth < 0.
+            || difficulty.enemy_health_growth < 0.
+            || difficulty.bullet_speed_growth < 0.
+            || difficulty.fire_rate_growth < 0.
+        {
+            return Err("difficulty growth can not be negative".to_string());
+        }
+        for preset in DifficultyPreset::ALL {
+            let preset_balance = self.presets.get(preset);
+            if preset_balance.starting_level < 0. {
+                return Err(format!(
+                    "starting level of {} can not be negative",
+                    preset.name()
+                ));
+            }
+            if preset_balance.spawn_rate_multiplier <= 0. {
+                return Err(format!(
+                    "spawn rate multiplier of {} has to be greater than zero",
+                    preset.name()
+                ));
+            }
+        }
+        if self.power_ups.dropped_lifetime_secs < 0. {
+            return Err("dropped power up lifetime can not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PlayerBalance {
+    pub health: i32,
+    pub speed: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub acceleration: f32,
+    pub steering: f32,
+    pub cannon_reload_ms: u64,
+    pub cannon_turn_rate: f32,
+    pub bullet_speed: f32,
+    pub bullet_damage: i32,
+    pub spread_shot_bullets: u32,
+    pub spread_shot_angle: f32,
+    pub spread_shot_reloads: u32,
+    pub invulnerability_ms: u64,
+    pub knockback_speed: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ObstacleBalance {
+    pub variants: Vec<ObstacleVariant>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ObstacleVariant {
+    pub shape: Shape,
+    pub health: i32,
+    pub damage: i32,
+    pub mass: Mass,
+    pub immune_to_bullets: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RiverBalance {
+    pub land_damage: i32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SideCannonBalance {
+    pub shape: Shape,
+    pub health: i32,
+    pub shooting_interval_ms: u64,
+    pub bullet_speed: f32,
+    pub bullet_damage: i32,
+    pub score: i32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PirateBalance {
+    pub shape: Shape,
+    pub health: i32,
+    pub collision_damage: i32,
+    pub invulnerability_ms: u64,
+    pub knockback_speed: f32,
+    pub shooting_interval_ms: u64,
+    pub bullet_speed: f32,
+    pub bullet_damage: i32,
+    pub score: i32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PowerUpBalance {
+    pub barrel_shape: Shape,
+    pub barrel_damage: i32,
+    pub repair_shape: Shape,
+    pub weapon_shape: Shape,
+    pub dropped_lifetime_secs: f32,
+    pub repair_max_health: i32,
+    pub repair_health: i32,
+    pub weapon_boost: f32,
+    pub weapon_duration_secs: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct DifficultyBalance {
+    pub distance_per_point: i32,
+    pub seconds_per_point: f32,
+    pub points_per_level: f32,
+    pub medium_level: f32,
+    pub hard_level: f32,
+    pub spawn_rate_growth: f32,
+    pub enemy_health_growth: f32,
+    pub bullet_speed_growth: f32,
+    pub fire_rate_growth: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PresetsBalance {
+    pub easy: PresetBalance,
+    pub normal: PresetBalance,
+    pub hard: PresetBalance,
+    pub nightmare: PresetBalance,
+}
+
+impl PresetsBalance {
+    pub fn get(&self, preset: DifficultyPreset) -> &PresetBalance {
+        match preset {
+            DifficultyPreset::Easy => &self.easy,
+            DifficultyPreset::Normal => &self.normal,
+            DifficultyPreset::Hard => &self.hard,
+            DifficultyPreset::Nightmare => &self.nightmare,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct PresetBalance {
+    pub starting_level: f32,
+    pub health_multiplier: f32,
+    pub damage_taken_multiplier: f32,
+    pub damage_dealt_multiplier: f32,
+    pub spawn_rate_multiplier: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnBalance {
+    pub threat_budget: f32,
+    pub waves: Vec<SpawnWave>,
+    pub table: Vec<SpawnEntry>,
+}
+
+/// Every wave spawns one entry of the spawn table each time its timer runs out
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnWave {
+    pub from_difficulty: Difficulty,
+    pub first_delay_secs: f32,
+    pub interval_ms: Range<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnEntry {
+    pub kind: SpawnKind,
+    pub weight: u32,
+    pub threat: f32,
+    pub from_difficulty: Difficulty,
+    pub from_distance: f32,
+    pub ahead: f32,
+}
+
+#[derive(Default)]
+struct BalanceLoader;
+
+impl AssetLoader for BalanceLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let balance = ron::de::from_bytes::<Balance>(bytes)?;
+            balance.validate().map_err(bevy::asset::Error::msg)?;
+            load_context.set_default_asset(LoadedAsset::new(balance));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["balance.ron"]
+    }
+}
+
+fn apply_balance(
+    mut balance_events: EventReader<AssetEvent<Balance>>,
+    balances: Res<Assets<Balance>>,
+    mut balance: ResMut<Balance>,
+) {
+    for event in balance_events.iter() {
+        if let AssetEvent::Created { handle } | AssetEvent::Modified { handle } = event {
+            if let Some(loaded) = balances.get(handle) {
+                info!("Applying game balance");
+                *balance = loaded.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundled_balance_is_valid() {
+        assert_eq!(Balance::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn balance_without_obstacles_is_rejected() {
+        let mut balance = Balance::default();
+        balance.obstacles.variants.clear();
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_without_player_speed_is_rejected() {
+        let mut balance = Balance::default();
+        balance.player.speed = 0.;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_with_min_speed_above_max_speed_is_rejected() {
+        let mut balance = Balance::default();
+        balance.player.min_speed = balance.player.max_speed + 1.;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_with_negative_first_delay_is_rejected() {
+        let mut balance = Balance::default();
+        balance.spawns.waves[0].first_delay_secs = -1.;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_with_negative_growth_is_rejected() {
+        let mut balance = Balance::default();
+        balance.difficulty.fire_rate_growth = -0.5;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_with_negative_starting_level_is_rejected() {
+        let mut balance = Balance::default();
+        balance.presets.easy.starting_level = -2.;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_without_spawn_rate_is_rejected() {
+        let mut balance = Balance::default();
+        balance.presets.hard.spawn_rate_multiplier = 0.;
+        assert!(balance.validate().is_err());
+    }
+
+    #[test]
+    fn balance_with_negative_power_up_lifetime_is_rejected() {
+        let mut balance = Balance::default();
+        balance.power_ups.dropped_lifetime_secs = -1.;
+        assert!(balance.validate().is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+pub struct CameraPlugin;
+
+/// This plugin spawns the point of view of the game.
+/// The simulation only needs its transform (spawning happens relative to it),
+/// the actual render camera is attached by the `GraphicsPlugin`
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(spawn_camera);
+    }
+}
+
+#[derive(Component)]
+pub struct MainCamera;
+
+pub const CAMERA_Z: f32 = 999.9;
+
+fn spawn_camera(mut commands: Commands) {
+    commands
+        .spawn(TransformBundle::from_transform(Transform::from_xyz(
+            0., 0., CAMERA_Z,
+        )))
+        .insert(MainCamera);
+}
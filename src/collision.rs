@@ -0,0 +1,338 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::{environment::Collidable, health::Health, simulation_running, SimulationSet};
+
+/// Side of one square cell of the [`SpatialGrid`], about the size of the bigger things on the river
+pub const GRID_CELL_SIZE: f32 = 64.;
+
+pub struct CollisionPlugin;
+
+/// This plugin keeps track of where everything that can be hit is.
+/// Collision checks ask the [`SpatialGrid`] for what is nearby instead of testing every entity,
+/// so they cost the same no matter how long the run has been going on
+impl Plugin for CollisionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialGrid>()
+            .add_system(
+                update_spatial_grid
+                    .after(SimulationSet::Movement)
+                    .before(SimulationSet::Collision)
+                    .run_if(simulation_running)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            // Runs every frame, despawns are only reported for a couple of frames
+            // and there may be frames without a simulation tick
+            .add_system(forget_despawned_entities.in_base_set(CoreSet::Last));
+    }
+}
+
+/// Outline of something that can be hit.
+/// It is centred on the entity's transform and turns together with it
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Shape {
+    Rectangle { size: Vec2 },
+    Circle { radius: f32 },
+}
+
+impl Shape {
+    /// Size of the axis aligned box around the shape when placed at the transform
+    pub fn bounds(&self, transform: &Transform) -> Vec2 {
+        match *self {
+            Shape::Rectangle { size } => {
+                let [x_axis, y_axis] = axes(transform);
+                x_axis.abs() * size.x + y_axis.abs() * size.y
+            }
+            Shape::Circle { radius } => Vec2::splat(radius * 2.),
+        }
+    }
+
+    /// Whether the two shapes placed at their transforms overlap, touching edges do not count
+    pub fn intersects(
+        &self,
+        transform: &Transform,
+        other: &Shape,
+        other_transform: &Transform,
+    ) -> bool {
+        let center = transform.translation.truncate();
+        let other_center = other_transform.translation.truncate();
+        match (*self, *other) {
+            (Shape::Rectangle { size }, Shape::Rectangle { size: other_size }) => {
+                rectangles_intersect(
+                    center,
+                    size / 2.,
+                    axes(transform),
+                    other_center,
+                    other_size / 2.,
+                    axes(other_transform),
+                )
+            }
+            (Shape::Rectangle { size }, Shape::Circle { radius }) => rectangle_intersects_circle(
+                center,
+                size / 2.,
+                axes(transform),
+                other_center,
+                radius,
+            ),
+            (Shape::Circle { radius }, Shape::Rectangle { size }) => rectangle_intersects_circle(
+                other_center,
+                size / 2.,
+                axes(other_transform),
+                center,
+                radius,
+            ),
+            (
+                Shape::Circle { radius },
+                Shape::Circle {
+                    radius: other_radius,
+                },
+            ) => center.distance_squared(other_center) < (radius + other_radius).powi(2),
+        }
+    }
+}
+
+/// Directions of the local x and y axes of the transform in the world
+fn axes(transform: &Transform) -> [Vec2; 2] {
+    [
+        (transform.rotation * Vec3::X).truncate().normalize(),
+        (transform.rotation * Vec3::Y).truncate().normalize(),
+    ]
+}
+
+/// Separating axis test, the rectangles overlap unless their projections
+/// onto one of their four edge directions are apart
+fn rectangles_intersect(
+    center: Vec2,
+    half_size: Vec2,
+    axes: [Vec2; 2],
+    other_center: Vec2,
+    other_half_size: Vec2,
+    other_axes: [Vec2; 2],
+) -> bool {
+    let offset = other_center - center;
+    axes.iter().chain(other_axes.iter()).all(|axis| {
+        let reach = half_size.x * axis.dot(axes[0]).abs() + half_size.y * axis.dot(axes[1]).abs();
+        let other_reach = other_half_size.x * axis.dot(other_axes[0]).abs()
+            + other_half_size.y * axis.dot(other_axes[1]).abs();
+        offset.dot(*axis).abs() < reach + other_reach
+    })
+}
+
+fn rectangle_intersects_circle(
+    center: Vec2,
+    half_size: Vec2,
+    axes: [Vec2; 2],
+    circle_center: Vec2,
+    radius: f32,
+) -> bool {
+    // Closest point of the rectangle to the circle, in the rectangle's own coordinates
+    let offset = circle_center - center;
+    let local = Vec2::new(offset.dot(axes[0]), offset.dot(axes[1]));
+    let closest = local.clamp(-half_size, half_size);
+    local.distance_squared(closest) < radius * radius
+}
+
+/// Uniform grid over the world listing the entities with [`Collidable`] or [`Health`] in each cell
+#[derive(Resource, Default)]
+pub struct SpatialGrid {
+    cells: HashMap<IVec2, Vec<Entity>>,
+    /// First and last cell covered by each entity in the grid
+    entries: HashMap<Entity, (IVec2, IVec2)>,
+}
+
+impl SpatialGrid {
+    /// Puts the entity into every cell the box around `center` touches, moving it if it was already in
+    pub fn insert(&mut self, entity: Entity, center: Vec2, size: Vec2) {
+        let range = cell_range(center, size);
+        if self.entries.get(&entity) == Some(&range) {
+            return;
+        }
+        self.remove(entity);
+        for cell in cells_in(range) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+        self.entries.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some(range) = self.entries.remove(&entity) else {
+            return;
+        };
+        for cell in cells_in(range) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|other| *other != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Entities sharing a cell with the box around `center`.
+    /// They are only candidates, whether they really overlap is up to the caller
+    pub fn query(&self, center: Vec2, size: Vec2) -> Vec<Entity> {
+        let mut found: Vec<Entity> = cells_in(cell_range(center, size))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .copied()
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+fn cell_range(center: Vec2, size: Vec2) -> (IVec2, IVec2) {
+    let half_size = size / 2.;
+    (
+        ((center - half_size) / GRID_CELL_SIZE).floor().as_ivec2(),
+        ((center + half_size) / GRID_CELL_SIZE).floor().as_ivec2(),
+    )
+}
+
+fn cells_in((min, max): (IVec2, IVec2)) -> impl Iterator<Item = IVec2> {
+    (min.x..=max.x).flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+}
+
+fn update_spatial_grid(
+    mut grid: ResMut<SpatialGrid>,
+    moved_q: Query<
+        (Entity, &Transform, Option<&Collidable>, Option<&Health>),
+        (
+            Or<(With<Collidable>, With<Health>)>,
+            Or<(Changed<Transform>, Added<Collidable>, Added<Health>)>,
+        ),
+    >,
+) {
+    for (entity, transform, collidable, health) in moved_q.iter() {
+        let size = collidable
+            .map(|collidable| collidable.shape.bounds(transform))
+            .unwrap_or_default()
+            .max(
+                health
+                    .map(|health| health.shape.bounds(transform))
+                    .unwrap_or_default(),
+            );
+        grid.insert(entity, transform.translation.truncate(), size);
+    }
+}
+
+fn forget_despawned_entities(
+    mut grid: ResMut<SpatialGrid>,
+    mut removed_collidables: RemovedComponents<Collidable>,
+    mut removed_healths: RemovedComponents<Health>,
+) {
+    for entity in removed_collidables.iter().chain(removed_healths.iter()) {
+        grid.remove(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    use super::*;
+
+    fn rectangle(width: f32, height: f32) -> Shape {
+        Shape::Rectangle {
+            size: Vec2::new(width, height),
+        }
+    }
+
+    fn at(x: f32, y: f32) -> Transform {
+        Transform::from_xyz(x, y, 0.)
+    }
+
+    fn turned(x: f32, y: f32, angle: f32) -> Transform {
+        at(x, y).with_rotation(Quat::from_rotation_z(angle))
+    }
+
+    #[test]
+    fn boxes_overlap_until_they_only_touch() {
+        let shape = rectangle(20., 10.);
+        assert!(shape.intersects(&at(0., 0.), &shape, &at(19., 9.)));
+        assert!(!shape.intersects(&at(0., 0.), &shape, &at(20., 0.)));
+        assert!(!shape.intersects(&at(0., 0.), &shape, &at(0., 10.)));
+    }
+
+    #[test]
+    fn turned_boxes_are_separated_along_their_own_edges() {
+        let square = rectangle(10., 10.);
+        // Axis aligned the corners would overlap, turned by 45° the gap between them shows
+        assert!(square.intersects(&at(0., 0.), &square, &at(9., 9.)));
+        assert!(!square.intersects(
+            &turned(0., 0., FRAC_PI_4),
+            &square,
+            &turned(9., 9., FRAC_PI_4)
+        ));
+        // A long box turned upright reaches what it would miss lying flat
+        let plank = rectangle(40., 4.);
+        assert!(!plank.intersects(&at(0., 0.), &square, &at(0., 15.)));
+        assert!(plank.intersects(&turned(0., 0., FRAC_PI_2), &square, &at(0., 15.)));
+    }
+
+    #[test]
+    fn circle_hits_box_at_its_closest_point() {
+        let shape = rectangle(20., 10.);
+        let circle = Shape::Circle { radius: 5. };
+        assert!(shape.intersects(&at(0., 0.), &circle, &at(14., 0.)));
+        assert!(!shape.intersects(&at(0., 0.), &circle, &at(15., 0.)));
+        // Next to the corner the circle misses, even though it is within the box's reach along both axes
+        assert!(!circle.intersects(&at(14., 9.), &shape, &at(0., 0.)));
+        assert!(circle.intersects(&at(12., 7.), &shape, &at(0., 0.)));
+    }
+
+    #[test]
+    fn circle_hits_turned_box_in_its_own_coordinates() {
+        let plank = rectangle(40., 4.);
+        let circle = Shape::Circle { radius: 3. };
+        assert!(!plank.intersects(&at(0., 0.), &circle, &at(0., 15.)));
+        assert!(plank.intersects(&turned(0., 0., FRAC_PI_2), &circle, &at(0., 15.)));
+    }
+
+    #[test]
+    fn circles_overlap_closer_than_their_radii() {
+        let small = Shape::Circle { radius: 5. };
+        let big = Shape::Circle { radius: 10. };
+        assert!(small.intersects(&at(0., 0.), &big, &at(14.9, 0.)));
+        assert!(!small.intersects(&at(0., 0.), &big, &at(15., 0.)));
+    }
+
+    #[test]
+    fn bounds_grow_when_a_box_turns() {
+        let shape = rectangle(20., 10.);
+        assert_eq!(shape.bounds(&at(0., 0.)), Vec2::new(20., 10.));
+        let upright = shape.bounds(&turned(0., 0., FRAC_PI_2));
+        assert!((upright - Vec2::new(10., 20.)).abs().max_element() < 1e-4);
+        let diagonal = shape.bounds(&turned(0., 0., FRAC_PI_4));
+        assert!(
+            (diagonal - Vec2::splat(30. * FRAC_PI_4.cos()))
+                .abs()
+                .max_element()
+                < 1e-4
+        );
+    }
+
+    #[test]
+    fn grid_finds_entities_in_touched_cells_only() {
+        let mut grid = SpatialGrid::default();
+        let near = Entity::from_raw(1);
+        let far = Entity::from_raw(2);
+        grid.insert(near, Vec2::new(10., 10.), Vec2::splat(20.));
+        grid.insert(far, Vec2::new(500., 500.), Vec2::splat(20.));
+        assert_eq!(
+            grid.query(Vec2::new(30., 30.), Vec2::splat(10.)),
+            vec![near]
+        );
+
+        grid.insert(near, Vec2::new(480., 480.), Vec2::splat(20.));
+        assert!(grid.query(Vec2::new(30., 30.), Vec2::splat(10.)).is_empty());
+
+        grid.remove(far);
+        assert_eq!(
+            grid.query(Vec2::new(500., 500.), Vec2::splat(10.)),
+            vec![near]
+        );
+    }
+}
@@ -0,0 +1,414 @@
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Binding, GameControl, InputMap, BINDING_SLOTS},
+    loading::FontAssets,
+    menu::{ButtonAction, ButtonColors},
+    GameState,
+};
+
+pub struct ControlsPlugin;
+
+/// This plugin draws the controls screen, reached from the menu.
+/// Every control can be bound to a couple of keys or mouse buttons,
+/// a key or button can only trigger one control and the bindings are saved right away.
+/// How the cannon is aimed is picked here too
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_system(setup_controls_screen.in_schedule(OnEnter(GameState::Controls)))
+            .add_systems(
+                (
+                    capture_binding,
+                    select_binding.after(capture_binding),
+                    reset_controls.after(capture_binding),
+                    switch_aim_mode.after(capture_binding),
+                    update_controls_screen
+                        .after(select_binding)
+                        .after(reset_controls)
+                        .after(switch_aim_mode),
+                )
+                    .in_set(OnUpdate(GameState::Controls)),
+            )
+            .add_system(cleanup_controls_screen.in_schedule(OnExit(GameState::Controls)));
+    }
+}
+
+const HINT: &str = "Click a binding, then press a key or mouse button. Delete clears it.";
+
+/// The binding slot waiting for a key or mouse button and what to tell the player
+#[derive(Resource)]
+struct Rebinding {
+    waiting: Option<(GameControl, usize)>,
+    message: String,
+}
+
+impl Default for Rebinding {
+    fn default() -> Self {
+        Rebinding {
+            waiting: None,
+            message: HINT.to_string(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ControlsScreen;
+
+#[derive(Component)]
+struct BindingButton {
+    control: GameControl,
+    slot: usize,
+}
+
+#[derive(Component)]
+struct ResetControlsButton;
+
+#[derive(Component)]
+struct AimModeButton;
+
+#[derive(Component)]
+struct ControlsMessage;
+
+fn setup_controls_screen(
+    mut commands: Commands,
+    font_assets: Res<FontAssets>,
+    button_colors: Res<ButtonColors>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    *rebinding = Rebinding::default();
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ControlsScreen)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Controls",
+                TextStyle {
+                    font: font_assets.fira_sans.clone(),
+                    font_size: 50.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+            for control in GameControl::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            size: Size::width(Val::Px(500.)),
+                            align_items: AlignItems::Center,
+                            margin: UiRect::top(Val::Px(8.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                control.name(),
+                                TextStyle {
+                                    font: font_assets.fira_sans.clone(),
+                                    font_size: 20.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            )
+                            .with_style(Style {
+                                size: Size::width(Val::Px(160.)),
+                                ..default()
+                            }),
+                        );
+                        for slot in 0..BINDING_SLOTS {
+                            parent
+                                .spawn(ButtonBundle {
+                                    style: Style {
+                                        size: Size::width(Val::Px(160.)),
+                                        margin: UiRect::horizontal(Val::Px(5.)),
+                                        padding: UiRect::all(Val::Px(8.)),
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    background_color: button_colors.normal.into(),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "",
+                                        TextStyle {
+                                            font: font_assets.fira_sans_reg.clone(),
+                                            font_size: 20.0,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                        },
+                                    ));
+                                })
+                                .insert(BindingButton { control, slot });
+                        }
+                    });
+            }
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::width(Val::Px(500.)),
+                        align_items: AlignItems::Center,
+                        margin: UiRect::vertical(Val::Px(8.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(
+                        TextBundle::from_section(
+                            "Aim with",
+                            TextStyle {
+                                font: font_assets.fira_sans.clone(),
+                                font_size: 20.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        )
+                        .with_style(Style {
+                            size: Size::width(Val::Px(160.)),
+                            ..default()
+                        }),
+                    );
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                size: Size::width(Val::Px(160.)),
+                                margin: UiRect::horizontal(Val::Px(5.)),
+                                padding: UiRect::all(Val::Px(8.)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "",
+                                TextStyle {
+                                    font: font_assets.fira_sans_reg.clone(),
+                                    font_size: 20.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ));
+                        })
+                        .insert(AimModeButton);
+                });
+            parent
+                .spawn(TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: font_assets.fira_sans_reg.clone(),
+                        font_size: 16.0,
+                        color: Color::rgb(0.8, 0.8, 0.8),
+                    },
+                ))
+                .insert(ControlsMessage);
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(20.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                margin: UiRect::horizontal(Val::Px(5.)),
+                                padding: UiRect::all(Val::Px(10.)),
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Reset to defaults",
+                                TextStyle {
+                                    font: font_assets.fira_sans_reg.clone(),
+                                    font_size: 24.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ));
+                        })
+                        .insert(ResetControlsButton);
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                margin: UiRect::horizontal(Val::Px(5.)),
+                                padding: UiRect::all(Val::Px(10.)),
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Back",
+                                TextStyle {
+                                    font: font_assets.fira_sans_reg.clone(),
+                                    font_size: 24.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ));
+                        })
+                        .insert(ButtonAction::BackButton);
+                });
+        });
+}
+
+/// Binds the first key or mouse button pressed while a slot is waiting for one
+fn capture_binding(
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+) {
+    let Some((control, slot)) = rebinding.waiting else {
+        return;
+    };
+    let binding = match keyboard_input.get_just_pressed().next() {
+        Some(KeyCode::Delete) => None,
+        Some(key) => Some(Binding::Key(*key)),
+        None => match mouse_input.get_just_pressed().next() {
+            Some(button) => Some(Binding::Mouse(*button)),
+            None => return,
+        },
+    };
+    rebinding.waiting = None;
+    rebinding.message = match input_map.bind(control, slot, binding) {
+        Ok(()) => {
+            input_map.save();
+            HINT.to_string()
+        }
+        Err(other) => format!(
+            "{} is already bound to {}",
+            binding.map(|binding| binding.name()).unwrap_or_default(),
+            other.name()
+        ),
+    };
+}
+
+fn select_binding(
+    mut rebinding: ResMut<Rebinding>,
+    binding_buttons_q: Query<(&Interaction, &BindingButton), Changed<Interaction>>,
+) {
+    if rebinding.waiting.is_some() {
+        return;
+    }
+    for (interaction, button) in binding_buttons_q.iter() {
+        if *interaction == Interaction::Clicked {
+            rebinding.waiting = Some((button.control, button.slot));
+            rebinding.message = format!(
+                "Press a key or mouse button to {}, Delete clears it",
+                button.control.name().to_lowercase()
+            );
+        }
+    }
+}
+
+fn reset_controls(
+    mut rebinding: ResMut<Rebinding>,
+    mut input_map: ResMut<InputMap>,
+    reset_button_q: Query<&Interaction, (Changed<Interaction>, With<ResetControlsButton>)>,
+) {
+    for interaction in reset_button_q.iter() {
+        if *interaction == Interaction::Clicked {
+            input_map.reset();
+            input_map.save();
+            *rebinding = Rebinding::default();
+        }
+    }
+}
+
+fn switch_aim_mode(
+    mut input_map: ResMut<InputMap>,
+    aim_mode_button_q: Query<&Interaction, (Changed<Interaction>, With<AimModeButton>)>,
+) {
+    for interaction in aim_mode_button_q.iter() {
+        if *interaction == Interaction::Clicked {
+            input_map.aim_mode = input_map.aim_mode.next();
+            input_map.save();
+        }
+    }
+}
+
+fn update_controls_screen(
+    rebinding: Res<Rebinding>,
+    input_map: Res<InputMap>,
+    button_colors: Res<ButtonColors>,
+    mut binding_buttons_q: Query<(
+        &Interaction,
+        &BindingButton,
+        &Children,
+        &mut BackgroundColor,
+    )>,
+    mut buttons_q: Query<
+        (
+            &Interaction,
+            Option<&AimModeButton>,
+            &Children,
+            &mut BackgroundColor,
+        ),
+        (
+            Or<(With<ResetControlsButton>, With<AimModeButton>)>,
+            Without<BindingButton>,
+        ),
+    >,
+    mut message_q: Query<&mut Text, With<ControlsMessage>>,
+    mut text_q: Query<&mut Text, Without<ControlsMessage>>,
+) {
+    for (interaction, button, children, mut color) in binding_buttons_q.iter_mut() {
+        let waiting = rebinding.waiting == Some((button.control, button.slot));
+        *color = if waiting {
+            button_colors.selected
+        } else if *interaction == Interaction::Hovered {
+            button_colors.hovered
+        } else {
+            button_colors.normal
+        }
+        .into();
+        let label = if waiting {
+            "...".to_string()
+        } else {
+            input_map.bindings(button.control)[button.slot]
+                .map_or_else(|| "-".to_string(), |binding| binding.name())
+        };
+        for child in children.iter() {
+            if let Ok(mut text) = text_q.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+    for (interaction, aim_mode_button, children, mut color) in buttons_q.iter_mut() {
+        *color = if *interaction == Interaction::Hovered {
+            button_colors.hovered
+        } else {
+            button_colors.normal
+        }
+        .into();
+        if aim_mode_button.is_some() {
+            for child in children.iter() {
+                if let Ok(mut text) = text_q.get_mut(*child) {
+                    text.sections[0].value = input_map.aim_mode.name().to_string();
+                }
+            }
+        }
+    }
+    for mut text in message_q.iter_mut() {
+        text.sections[0].value = rebinding.message.clone();
+    }
+}
+
+fn cleanup_controls_screen(mut commands: Commands, screen: Query<Entity, With<ControlsScreen>>) {
+    commands.entity(screen.single()).despawn_recursive();
+}
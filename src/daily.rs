@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+
+use crate::{
+    difficulty::DifficultyPreset,
+    random::{seed_run, FixedSeed},
+    replay::use_replay_settings,
+    score::GameScore,
+    storage, GameState,
+};
+
+pub struct DailyPlugin;
+
+/// This plugin turns runs into the daily challenge when that mode is picked.
+/// Everyone playing on the same calendar day (in UTC) gets the same seed and plays on the normal preset,
+/// so the river, obstacles, enemies and drops are identical for all of them
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<DailyChallenge>()
+            .add_system(
+                pick_daily_challenge
+                    .after(use_replay_settings)
+                    .before(seed_run)
+                    .in_schedule(OnEnter(GameState::Init)),
+            );
+    }
+}
+
+/// Chosen in the menu, it stays the same for every run until another one is chosen
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameMode {
+    #[default]
+    Classic,
+    Daily,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 2] = [GameMode::Classic, GameMode::Daily];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Classic => "Classic",
+            GameMode::Daily => "Daily",
+        }
+    }
+}
+
+/// Day of the challenge the current run is playing, in days since the unix epoch.
+/// Not set when the run is a classic one
+/// or the seed was fixed some other way, like when playing a replay back
+#[derive(Resource, Default)]
+pub struct DailyChallenge(pub Option<u64>);
+
+impl DailyChallenge {
+    pub fn seed(&self) -> Option<u64> {
+        self.0.map(daily_seed)
+    }
+}
+
+/// The current calendar day in UTC, in days since the unix epoch
+pub fn today() -> u64 {
+    storage::now() / 86_400
+}
+
+/// Seed of the daily challenge of the given day, scrambled so that following days look nothing alike
+pub fn daily_seed(day: u64) -> u64 {
+    // SplitMix64 finalizer
+    let mut seed = day.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    seed = (seed ^ (seed >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    seed = (seed ^ (seed >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    seed ^ (seed >> 31)
+}
+
+fn pick_daily_challenge(
+    mode: Res<GameMode>,
+    fixed_seed: Res<FixedSeed>,
+    mut challenge: ResMut<DailyChallenge>,
+    mut game_score: ResMut<GameScore>,
+) {
+    challenge.0 = (*mode == GameMode::Daily && fixed_seed.0.is_none()).then(today);
+    if challenge.0.is_some() {
+        game_score.preset = DifficultyPreset::Normal;
+    }
+}
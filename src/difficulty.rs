@@ -1,46 +1,108 @@
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{score::GameScore, GameState};
+use crate::{balance::Balance, score::GameScore, simulation_running, GameState, SimulationSet};
 
-#[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
+/// Named tiers of the [`DifficultyLevel`], unlocking spawn waves and telling the player how bad it is
+#[derive(States, Default, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Deserialize)]
 pub enum Difficulty {
-    // During the loading State the LoadingPlugin will load our assets
     #[default]
     Initial,
     Medium,
     Hard,
 }
 
+/// Chosen in the menu, it stays the same for every run until another one is chosen.
+/// Runs are played on the copy in [`GameScore::preset`], replays and the daily challenge pick their own there.
+/// What each of them changes is set in the presets of the [`Balance`]
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DifficultyPreset {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl DifficultyPreset {
+    pub const ALL: [DifficultyPreset; 4] = [
+        DifficultyPreset::Easy,
+        DifficultyPreset::Normal,
+        DifficultyPreset::Hard,
+        DifficultyPreset::Nightmare,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DifficultyPreset::Easy => "Easy",
+            DifficultyPreset::Normal => "Normal",
+            DifficultyPreset::Hard => "Hard",
+            DifficultyPreset::Nightmare => "Nightmare",
+        }
+    }
+}
+
+/// How hard the run is at the moment, starts at zero and keeps growing for as long as the run goes on
+#[derive(Resource, Default)]
+pub struct DifficultyLevel(pub f32);
+
+impl DifficultyLevel {
+    /// Multiplier for something that grows by `growth` of its base value per level
+    pub fn scale(&self, growth: f32) -> f32 {
+        1. + growth * self.0
+    }
+}
+
 pub struct DifficultyPlugin;
 
+/// This plugin derives the [`DifficultyLevel`] from the traveled distance, time and score
+/// and switches the [`Difficulty`] tiers accordingly.
+/// The level is updated at the end of every tick, so every system of a tick sees the same value
 impl Plugin for DifficultyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(change_difficulty.in_set(OnUpdate(GameState::Playing)))
+        app.init_resource::<DifficultyLevel>()
+            .init_resource::<DifficultyPreset>()
+            .add_system(
+                change_difficulty
+                    .after(SimulationSet::Logic)
+                    .run_if(simulation_running)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
             .add_system(restart_difficulty.in_schedule(OnEnter(GameState::Restart)));
     }
 }
 
 fn change_difficulty(
     game_score: Res<GameScore>,
+    mut level: ResMut<DifficultyLevel>,
     state: Res<State<Difficulty>>,
     mut next_state: ResMut<NextState<Difficulty>>,
+    balance: Res<Balance>,
 ) {
-    let score = game_score.score + game_score.distance_traveled as i32 / 50;
-    match state.0 {
-        Difficulty::Initial => {
-            if score > 200 {
-                next_state.set(Difficulty::Medium);
-            }
-        }
-        Difficulty::Medium => {
-            if score > 400 {
-                next_state.set(Difficulty::Hard);
-            }
-        }
-        _ => {}
+    let starting_level = balance.presets.get(game_score.preset).starting_level;
+    let balance = &balance.difficulty;
+    let points = game_score.score as f32
+        + game_score.distance_traveled / balance.distance_per_point as f32
+        + game_score.elapsed_time.elapsed_secs() / balance.seconds_per_point;
+    level.0 = starting_level + points / balance.points_per_level;
+
+    let tier = if level.0 >= balance.hard_level {
+        Difficulty::Hard
+    } else if level.0 >= balance.medium_level {
+        Difficulty::Medium
+    } else {
+        Difficulty::Initial
+    };
+    // Tiers only ever go up, points can not be lost
+    if tier > state.0 {
+        next_state.set(tier);
     }
 }
 
-fn restart_difficulty(mut next_state: ResMut<NextState<Difficulty>>) {
+fn restart_difficulty(
+    mut level: ResMut<DifficultyLevel>,
+    mut next_state: ResMut<NextState<Difficulty>>,
+) {
+    *level = DifficultyLevel::default();
     next_state.set(Difficulty::Initial);
 }
@@ -1,9 +1,14 @@
 use bevy::prelude::*;
 
 use crate::{
+    balance::Balance,
+    daily::{today, DailyChallenge, GameMode},
+    high_scores::{record_high_score, spawn_high_score_table, HighScores, NewHighScore},
     loading::FontAssets,
     menu::{ButtonAction, ButtonColors},
+    random::GameRng,
     score::GameScore,
+    stats::RunStats,
     GameState,
 };
 
@@ -11,8 +16,12 @@ pub struct EndPlugin;
 
 impl Plugin for EndPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(setup_end_ui.in_schedule(OnEnter(GameState::End)))
-            .add_system(cleanup_menu.in_schedule(OnExit(GameState::End)));
+        app.add_system(
+            setup_end_ui
+                .after(record_high_score)
+                .in_schedule(OnEnter(GameState::End)),
+        )
+        .add_system(cleanup_menu.in_schedule(OnExit(GameState::End)));
     }
 }
 
@@ -24,14 +33,23 @@ fn setup_end_ui(
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
     game_score: Res<GameScore>,
+    game_rng: Res<GameRng>,
+    run_stats: Res<RunStats>,
+    high_scores: Res<HighScores>,
+    new_high_score: Res<NewHighScore>,
+    mode: Res<GameMode>,
+    challenge: Res<DailyChallenge>,
+    balance: Res<Balance>,
 ) {
     commands
         .spawn(NodeBundle {
             style: Style {
                 size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
-                flex_direction: FlexDirection::Column,
+                // The run stats sit next to the panel, the high scores wrap below them
+                flex_wrap: FlexWrap::Wrap,
                 justify_content: JustifyContent::Center,
                 align_items: AlignItems::Center,
+                align_content: AlignContent::Center,
                 position_type: PositionType::Absolute,
                 ..default()
             },
@@ -42,7 +60,7 @@ fn setup_end_ui(
             parent
                 .spawn(NodeBundle {
                     style: Style {
-                        size: Size::new(Val::Px(400.), Val::Px(300.)),
+                        size: Size::new(Val::Px(400.), Val::Px(330.)),
                         justify_content: JustifyContent::Center,
                         flex_direction: FlexDirection::Column,
                         align_items: AlignItems::Center,
@@ -71,7 +89,7 @@ fn setup_end_ui(
                         TextBundle::from_section(
                             format!(
                                 "Final score: {}",
-                                game_score.score + game_score.distance_traveled as i32 / 50
+                                game_score.final_score(&balance.difficulty)
                             ),
                             TextStyle {
                                 font: font_assets.fira_sans_reg.clone(),
@@ -102,6 +120,33 @@ fn setup_end_ui(
                             ..default()
                         }),
                     );
+                    parent.spawn(
+                        TextBundle::from_section(
+                            format!("Difficulty: {}", game_score.preset.name()),
+                            TextStyle {
+                                font: font_assets.fira_sans_reg.clone(),
+                                font_size: 24.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::Center)
+                        .with_style(Style {
+                            flex_wrap: FlexWrap::Wrap,
+                            max_size: Size::width(Val::Px(300.)),
+                            ..default()
+                        }),
+                    );
+                    parent.spawn(
+                        TextBundle::from_section(
+                            format!("Seed: {}", game_rng.seed()),
+                            TextStyle {
+                                font: font_assets.fira_mono.clone(),
+                                font_size: 16.0,
+                                color: Color::rgb(0.8, 0.8, 0.8),
+                            },
+                        )
+                        .with_text_alignment(TextAlignment::Center),
+                    );
                     parent
                         .spawn(NodeBundle {
                             style: Style {
@@ -175,6 +220,73 @@ fn setup_end_ui(
                                 .insert(ButtonAction::ExitButton);
                         });
                 });
+            spawn_run_stats(parent, &font_assets, &run_stats);
+            spawn_high_score_table(
+                parent,
+                &font_assets,
+                &high_scores,
+                *mode,
+                challenge.0.unwrap_or_else(today),
+                &new_high_score,
+            );
+        });
+}
+
+/// Breakdown of the run next to the final score
+fn spawn_run_stats(parent: &mut ChildBuilder, font_assets: &FontAssets, run_stats: &RunStats) {
+    let accuracy = match run_stats.accuracy() {
+        Some(accuracy) => format!("{} ({:.0}%)", run_stats.hits, accuracy * 100.),
+        None => run_stats.hits.to_string(),
+    };
+    let closest_call = match run_stats.closest_call {
+        Some(health) => format!("{} health left", health),
+        None => "never hit".to_string(),
+    };
+    let damage_taken = &run_stats.damage_taken;
+    let lines = [
+        ("Shots fired", run_stats.shots_fired.to_string()),
+        ("Hits", accuracy),
+        ("Side cannons", run_stats.side_cannons_destroyed.to_string()),
+        ("Pirates", run_stats.pirates_destroyed.to_string()),
+        ("Logs broken", run_stats.logs_broken.to_string()),
+        ("Barrels opened", run_stats.barrels_opened.to_string()),
+        ("Repairs", run_stats.repairs_collected.to_string()),
+        (
+            "Weapon boosts",
+            run_stats.weapon_boosts_collected.to_string(),
+        ),
+        ("Damage taken", damage_taken.total().to_string()),
+        ("  obstacles", damage_taken.obstacles.to_string()),
+        ("  land", damage_taken.land.to_string()),
+        ("  barrels", damage_taken.barrels.to_string()),
+        ("  side cannons", damage_taken.side_cannons.to_string()),
+        ("  pirates", damage_taken.pirates.to_string()),
+        ("  stray bullets", damage_taken.other.to_string()),
+        ("Closest call", closest_call),
+    ];
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::height(Val::Px(330.)),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                padding: UiRect::all(Val::Px(15.)),
+                ..default()
+            },
+            background_color: Color::rgb(0.35, 0.35, 0.35).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            for (label, value) in lines {
+                parent.spawn(TextBundle::from_section(
+                    format!("{:<15}{:>16}", label, value),
+                    TextStyle {
+                        font: font_assets.fira_mono.clone(),
+                        font_size: 14.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                ));
+            }
         });
 }
 
@@ -1,53 +1,52 @@
 use std::{f32::consts::PI, time::Duration};
 
-use bevy::{prelude::*, sprite::collide_aabb::collide};
-use bevy_kira_audio::{Audio, AudioControl};
+use bevy::prelude::*;
 use rand::Rng;
 
 use crate::{
-    difficulty::Difficulty,
+    audio::SoundEvent,
+    balance::{Balance, DifficultyBalance},
+    camera::MainCamera,
+    collision::{Shape, SpatialGrid},
+    difficulty::DifficultyLevel,
     environment::{Collidable, LAND_TILE_SIZE, MAP_WIDTH},
-    health::{Bullet, Health, Mass},
-    loading::{AudioAssets, TextureAssets},
-    menu::MainCamera,
+    health::{
+        apply_knockback, Bullet, DeathEvent, Health, Invulnerability, Knockback, Mass,
+        BULLET_MAX_AGE, BULLET_RANGE,
+    },
+    lifetime::{Lifetime, OUT_OF_SIGHT},
     player::{Movement, Player},
+    random::{GameRng, RngStream},
     score::GameScore,
-    GameState,
+    spawn::{direct_spawns, SpawnEvent, SpawnKind, Threat},
+    GameState, SimulationSet,
 };
 
 pub struct EnemyPlugin;
 
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<EnemySpawnTimers>()
-            .add_system(spawn_enemies_onside.in_set(OnUpdate(GameState::Playing)))
-            .add_system(spawn_pirates.in_set(OnUpdate(GameState::Playing)))
-            .add_system(despawn_enemies.in_schedule(OnEnter(GameState::Restart)))
-            .add_system(enemies_shoot_at_player.in_set(OnUpdate(GameState::Playing)))
-            .add_system(pirates_shoot_at_player.in_set(OnUpdate(GameState::Playing)))
-            .add_system(despawn_enemies_out_of_sight.in_set(OnUpdate(GameState::Playing)))
-            .add_system(detect_killed_enemies.in_set(OnUpdate(GameState::Playing)))
-            .add_system(detect_killed_pirates.in_set(OnUpdate(GameState::Playing)))
-            .add_system(enemies_face_player.in_set(OnUpdate(GameState::Playing)))
-            .add_system(display_pirate_damage.in_set(OnUpdate(GameState::Playing)))
-            .add_system(pirate_cannons_face_player.in_set(OnUpdate(GameState::Playing)))
-            .add_system(increase_difficulty_medium.in_schedule(OnEnter(Difficulty::Medium)))
-            .add_system(increase_difficulty_hard.in_schedule(OnEnter(Difficulty::Hard)));
-    }
-}
-
-#[derive(Resource)]
-pub struct EnemySpawnTimers {
-    side_cannons: Vec<Timer>,
-    pirate_ships: Vec<Timer>,
-}
-
-impl Default for EnemySpawnTimers {
-    fn default() -> Self {
-        EnemySpawnTimers {
-            side_cannons: vec![Timer::new(Duration::from_secs(5), TimerMode::Repeating)],
-            pirate_ships: vec![],
-        }
+        app.add_systems(
+            (enemies_face_player, pirate_cannons_face_player)
+                // Aim at where the player is after moving this tick
+                .after(apply_knockback)
+                .in_set(SimulationSet::Movement)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
+        .add_systems(
+            (
+                spawn_enemies_onside.after(direct_spawns),
+                spawn_pirates.after(spawn_enemies_onside),
+                // Nothing shoots anymore in the tick it was killed
+                enemies_shoot_at_player.after(detect_killed_enemies),
+                pirates_shoot_at_player.after(detect_killed_pirates),
+                detect_killed_enemies,
+                detect_killed_pirates,
+            )
+                .in_set(SimulationSet::Logic)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
+        .add_system(despawn_enemies.in_schedule(OnEnter(GameState::Restart)));
     }
 }
 
@@ -55,7 +54,7 @@ impl Default for EnemySpawnTimers {
 pub struct Enemy {
     vector: Vec2,
     shooting_timer: Timer,
-    is_alive: bool,
+    pub is_alive: bool,
 }
 
 #[derive(Component)]
@@ -68,11 +67,11 @@ pub struct EnemyPirateCannon {
     is_alive: bool,
 }
 
-impl Default for Enemy {
-    fn default() -> Self {
+impl Enemy {
+    fn new(shooting_interval: Duration) -> Self {
         Enemy {
             vector: Vec2::new(0., 0.),
-            shooting_timer: Timer::new(Duration::from_secs(2), TimerMode::Repeating),
+            shooting_timer: Timer::new(shooting_interval, TimerMode::Repeating),
             is_alive: true,
         }
     }
@@ -83,137 +82,158 @@ enum SpawnPosition {
     Right,
 }
 
-pub const PIRATE_SIZE: Vec2 = Vec2::new(32., 64.);
-
 fn spawn_enemies_onside(
     mut commands: Commands,
-    time: Res<Time>,
-    mut spawn_timers: ResMut<EnemySpawnTimers>,
-    textures: Res<TextureAssets>,
-    camera_query: Query<&Transform, With<MainCamera>>,
+    mut spawn_events: EventReader<SpawnEvent>,
+    mut game_rng: ResMut<GameRng>,
+    level: Res<DifficultyLevel>,
+    balance: Res<Balance>,
 ) {
-    let camera_position = camera_query.get_single().unwrap().translation.y;
-    let next_spawn_position = camera_position + 600.;
-    for timer in &mut spawn_timers.side_cannons {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let position = get_random_spawn_position();
-            let x = if let SpawnPosition::Right = position {
-                MAP_WIDTH + LAND_TILE_SIZE
-            } else {
-                0.0 - LAND_TILE_SIZE
-            };
-            commands
-                .spawn(SpriteBundle {
-                    texture: textures.enemy_cannon.clone(),
-                    transform: Transform::from_translation(Vec3::new(x, next_spawn_position, 4.)),
-                    ..Default::default()
-                })
-                .insert(Enemy {
-                    ..Default::default()
-                })
-                .insert(Health {
-                    max_health: 2,
-                    health_amount: 2,
-                    size: Vec2::new(64., 64.),
-                    immune_to_bullets: false,
-                    mass: Mass::Wood,
-                });
-            let mut rng = rand::thread_rng();
-            let duration = rng.gen_range(4500..7000);
-            timer.set_duration(Duration::from_millis(duration));
-        }
+    let growth = &balance.difficulty;
+    let balance = &balance.side_cannons;
+    let health = scaled_health(balance.health, &level, growth);
+    let shooting_interval = scaled_shooting_interval(balance.shooting_interval_ms, &level, growth);
+    let rng = game_rng.stream(RngStream::Enemies);
+    for event in spawn_events
+        .iter()
+        .filter(|event| event.kind == SpawnKind::SideCannon)
+    {
+        let position = get_random_spawn_position(rng);
+        let x = if let SpawnPosition::Right = position {
+            MAP_WIDTH + LAND_TILE_SIZE
+        } else {
+            0.0 - LAND_TILE_SIZE
+        };
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(x, event.y, 4.)),
+                ..Default::default()
+            })
+            .insert(Enemy::new(shooting_interval))
+            .insert(Lifetime::behind_camera(OUT_OF_SIGHT))
+            .insert(Threat(event.threat))
+            .insert(Health {
+                max_health: health,
+                health_amount: health,
+                shape: balance.shape,
+                immune_to_bullets: false,
+                mass: Mass::Wood,
+            });
     }
 }
 
 fn spawn_pirates(
     mut commands: Commands,
-    time: Res<Time>,
-    mut spawn_timers: ResMut<EnemySpawnTimers>,
-    textures: Res<TextureAssets>,
+    mut spawn_events: EventReader<SpawnEvent>,
     obstacles_q: Query<(&Transform, &Collidable), Without<MainCamera>>,
-    camera_query: Query<&Transform, With<MainCamera>>,
+    grid: Res<SpatialGrid>,
+    mut game_rng: ResMut<GameRng>,
+    level: Res<DifficultyLevel>,
+    balance: Res<Balance>,
 ) {
-    let camera_position = camera_query.get_single().unwrap().translation.y;
-    let next_spawn_position = camera_position + 550.;
-    for timer in &mut spawn_timers.pirate_ships {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let position = get_random_pirate_spawn_position(&obstacles_q, next_spawn_position);
-            let mut rng = rand::thread_rng();
-            let random_angle = rng.gen_range(0.0..2. * PI);
-            commands
-                .spawn(SpriteBundle {
-                    texture: textures.enemy_pirate1.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        position,
-                        next_spawn_position,
-                        4.,
-                    ))
-                    .with_rotation(Quat::from_rotation_z(random_angle)),
-                    ..Default::default()
-                })
-                .insert(EnemyPirate)
-                .insert(Health {
-                    max_health: 3,
-                    health_amount: 3,
-                    size: PIRATE_SIZE,
-                    immune_to_bullets: false,
-                    mass: Mass::Wood,
-                })
-                .insert(Collidable {
-                    damage: 5,
-                    size: PIRATE_SIZE,
-                    is_alive: true,
-                })
-                .with_children(|parent| {
-                    parent
-                        .spawn(SpriteBundle {
-                            texture: textures.boat_cannon.clone(),
-                            transform: Transform::from_translation(Vec3::new(0., 20., 5.1))
-                                .with_rotation(Quat::from_rotation_z(0.)),
-                            ..Default::default()
-                        })
-                        .insert(EnemyPirateCannon {
-                            vector: Vec2::new(0., 0.),
-                            shooting_timer: Timer::new(
-                                Duration::from_millis(2000),
-                                TimerMode::Repeating,
-                            ),
-                            is_alive: true,
-                        });
-                });
-            let duration = rng.gen_range(13000..17000);
-            timer.set_duration(Duration::from_millis(duration));
-        }
+    let growth = &balance.difficulty;
+    let balance = &balance.pirates;
+    let health = scaled_health(balance.health, &level, growth);
+    let shooting_interval = scaled_shooting_interval(balance.shooting_interval_ms, &level, growth);
+    let rng = game_rng.stream(RngStream::Enemies);
+    for event in spawn_events
+        .iter()
+        .filter(|event| event.kind == SpawnKind::Pirate)
+    {
+        let rotation = Quat::from_rotation_z(rng.gen_range(0.0..2. * PI));
+        let Some(position) = get_random_pirate_spawn_position(
+            &obstacles_q,
+            &grid,
+            balance.shape,
+            event.y,
+            rotation,
+            rng,
+        ) else {
+            continue;
+        };
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(position, event.y, 4.))
+                    .with_rotation(rotation),
+                ..Default::default()
+            })
+            .insert(EnemyPirate)
+            .insert(Lifetime::behind_camera(OUT_OF_SIGHT))
+            .insert(Threat(event.threat))
+            .insert(Health {
+                max_health: health,
+                health_amount: health,
+                shape: balance.shape,
+                immune_to_bullets: false,
+                mass: Mass::Wood,
+            })
+            .insert(Collidable {
+                damage: balance.collision_damage,
+                shape: balance.shape,
+                is_alive: true,
+            })
+            .insert(Invulnerability::new(Duration::from_millis(
+                balance.invulnerability_ms,
+            )))
+            .insert(Knockback::new(balance.knockback_speed))
+            .with_children(|parent| {
+                parent
+                    .spawn(SpatialBundle {
+                        transform: Transform::from_translation(Vec3::new(0., 20., 5.1))
+                            .with_rotation(Quat::from_rotation_z(0.)),
+                        ..Default::default()
+                    })
+                    .insert(EnemyPirateCannon {
+                        vector: Vec2::new(0., 0.),
+                        shooting_timer: Timer::new(shooting_interval, TimerMode::Repeating),
+                        is_alive: true,
+                    });
+            });
     }
 }
 
+/// How many random positions are tried for a pirate ship before its spawn is skipped
+const PIRATE_SPAWN_ATTEMPTS: usize = 10;
+
+/// A spot across the river where the pirate ship does not overlap anything,
+/// none when the river is too crowded there
 pub fn get_random_pirate_spawn_position(
     obstacles_q: &Query<(&Transform, &Collidable), Without<MainCamera>>,
+    grid: &SpatialGrid,
+    shape: Shape,
     y: f32,
-) -> f32 {
-    let mut rng = rand::thread_rng();
-    let x = rng.gen_range(40.0..MAP_WIDTH - 40.);
-    let is_colliding = obstacles_q
-        .iter()
-        .any(|(collidable_transform, collidable)| {
-            let collision = collide(
-                Vec3::new(x, y, 1.),
-                PIRATE_SIZE,
-                collidable_transform.translation,
-                collidable.size,
-            );
-            return collision.is_some();
-        });
-    if is_colliding {
-        return get_random_pirate_spawn_position(obstacles_q, y);
-    }
-    return x;
+    rotation: Quat,
+    rng: &mut impl Rng,
+) -> Option<f32> {
+    (0..PIRATE_SPAWN_ATTEMPTS).find_map(|_| {
+        let x = rng.gen_range(40.0..MAP_WIDTH - 40.);
+        let transform = Transform::from_xyz(x, y, 1.).with_rotation(rotation);
+        let is_colliding = grid
+            .query(Vec2::new(x, y), shape.bounds(&transform))
+            .into_iter()
+            .filter_map(|entity| obstacles_q.get(entity).ok())
+            .any(|(collidable_transform, collidable)| {
+                shape.intersects(&transform, &collidable.shape, collidable_transform)
+            });
+        (!is_colliding).then_some(x)
+    })
+}
+
+/// Enemies spawn with more health the higher the difficulty level is at that time
+fn scaled_health(health: i32, level: &DifficultyLevel, growth: &DifficultyBalance) -> i32 {
+    (health as f32 * level.scale(growth.enemy_health_growth)).round() as i32
 }
 
-fn get_random_spawn_position() -> SpawnPosition {
-    let mut rng = rand::thread_rng();
+/// Enemies spawn shooting more often the higher the difficulty level is at that time
+fn scaled_shooting_interval(
+    shooting_interval_ms: u64,
+    level: &DifficultyLevel,
+    growth: &DifficultyBalance,
+) -> Duration {
+    Duration::from_millis(shooting_interval_ms).div_f32(level.scale(growth.fire_rate_growth))
+}
+
+fn get_random_spawn_position(rng: &mut impl Rng) -> SpawnPosition {
     if rng.gen::<bool>() {
         SpawnPosition::Left
     } else {
@@ -221,28 +241,13 @@ fn get_random_spawn_position() -> SpawnPosition {
     }
 }
 
-pub fn despawn_enemies_out_of_sight(
-    mut commands: Commands,
-    enemy_query: Query<(Entity, &Transform), Or<(With<Enemy>, With<EnemyPirate>)>>,
-    camera_query: Query<&Transform, With<MainCamera>>,
-) {
-    let camera_translation = camera_query.get_single().unwrap().translation;
-    for (enemy_entity, transform) in enemy_query.iter() {
-        if transform.translation.y < camera_translation.y - 600. {
-            commands.entity(enemy_entity).despawn_recursive();
-        }
-    }
-}
-
 pub fn despawn_enemies(
     mut commands: Commands,
     enemy_query: Query<Entity, Or<(With<Enemy>, With<EnemyPirate>)>>,
-    mut spawn_timers: ResMut<EnemySpawnTimers>,
 ) {
     for enemy_entity in enemy_query.iter() {
         commands.entity(enemy_entity).despawn_recursive();
     }
-    *spawn_timers = EnemySpawnTimers::default();
 }
 
 fn enemies_face_player(
@@ -252,11 +257,11 @@ fn enemies_face_player(
     let player_translation = player_query.get_single().unwrap().translation;
 
     for (mut transform, mut enemy) in transform_query.iter_mut() {
-        if enemy.is_alive == false {
+        if !enemy.is_alive {
             continue;
         }
         let mut vector = (player_translation - transform.translation).truncate();
-        vector.y = vector.y + 100.;
+        vector.y += 100.;
         enemy.vector = vector.normalize();
         let angle = enemy.vector.y.atan2(enemy.vector.x) - PI / 2.0;
         transform.rotation = Quat::from_rotation_z(angle);
@@ -264,31 +269,24 @@ fn enemies_face_player(
 }
 
 fn pirate_cannons_face_player(
-    mut transform_query: Query<
-        (
-            &mut Transform,
-            &GlobalTransform,
-            &mut EnemyPirateCannon,
-            &Parent,
-        ),
-        Without<Player>,
-    >,
+    mut transform_query: Query<(&mut Transform, &mut EnemyPirateCannon, &Parent), Without<Player>>,
     parent_query: Query<&Transform, (Without<Player>, Without<EnemyPirateCannon>)>,
     player_query: Query<&Transform, With<Player>>,
 ) {
     let player_translation = player_query.get_single().unwrap().translation;
 
-    for (mut cannon_transform, cannon_global_transform, mut enemy_cannon, enemy_parent) in
-        transform_query.iter_mut()
-    {
-        if enemy_cannon.is_alive == false {
+    for (mut cannon_transform, mut enemy_cannon, enemy_parent) in transform_query.iter_mut() {
+        if !enemy_cannon.is_alive {
             continue;
         }
         let parent_transform = parent_query.get(enemy_parent.get()).unwrap();
         let parent_angle = parent_transform.rotation.to_euler(EulerRot::YXZ);
 
-        let mut vector = (player_translation - cannon_global_transform.translation()).truncate();
-        vector.y = vector.y + 50.;
+        let cannon_translation = parent_transform
+            .mul_transform(*cannon_transform)
+            .translation;
+        let mut vector = (player_translation - cannon_translation).truncate();
+        vector.y += 50.;
 
         enemy_cannon.vector = vector.normalize();
 
@@ -300,21 +298,20 @@ fn pirate_cannons_face_player(
 fn enemies_shoot_at_player(
     mut commands: Commands,
     mut shooters_query: Query<(&mut Enemy, &Transform, Entity)>,
-    time: Res<Time>,
-    textures: Res<TextureAssets>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    fixed_time: Res<FixedTime>,
+    level: Res<DifficultyLevel>,
+    balance: Res<Balance>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
     for (mut enemy, transform, enemy_entity) in shooters_query.iter_mut() {
-        enemy.shooting_timer.tick(time.delta());
-        if enemy.is_alive == false {
+        enemy.shooting_timer.tick(fixed_time.period);
+        if !enemy.is_alive {
             continue;
         }
         if enemy.shooting_timer.finished() {
             let enemy_translation = transform.translation.truncate();
             commands
-                .spawn(SpriteBundle {
-                    texture: textures.bullet.clone(),
+                .spawn(SpatialBundle {
                     transform: Transform::from_translation(Vec3::new(
                         enemy_translation.x,
                         enemy_translation.y,
@@ -322,37 +319,47 @@ fn enemies_shoot_at_player(
                     )),
                     ..Default::default()
                 })
-                .insert(Bullet::new(enemy_entity))
+                .insert(Bullet::new(
+                    enemy_entity,
+                    balance.side_cannons.bullet_damage,
+                ))
+                .insert(
+                    Lifetime::behind_camera(OUT_OF_SIGHT)
+                        .with_max_range(BULLET_RANGE)
+                        .with_max_age(BULLET_MAX_AGE),
+                )
                 .insert(Movement {
                     vector: enemy.vector,
-                    speed: 350.0,
-                    ..Default::default()
+                    speed: balance.side_cannons.bullet_speed
+                        * level.scale(balance.difficulty.bullet_speed_growth),
                 });
-            audio
-                .play(audio_assets.bullet_fire.clone())
-                .with_volume(0.3);
+            sound_events.send(SoundEvent::EnemyShot);
         }
     }
 }
 
 fn pirates_shoot_at_player(
     mut commands: Commands,
-    mut shooters_query: Query<(&mut EnemyPirateCannon, &GlobalTransform, &Parent)>,
-    time: Res<Time>,
-    textures: Res<TextureAssets>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut shooters_query: Query<(&mut EnemyPirateCannon, &Transform, &Parent)>,
+    pirates_query: Query<&Transform, With<EnemyPirate>>,
+    fixed_time: Res<FixedTime>,
+    level: Res<DifficultyLevel>,
+    balance: Res<Balance>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
-    for (mut enemy_cannon, global_transform, enemy_pirate) in shooters_query.iter_mut() {
-        enemy_cannon.shooting_timer.tick(time.delta());
-        if enemy_cannon.is_alive == false {
+    for (mut enemy_cannon, cannon_transform, enemy_pirate) in shooters_query.iter_mut() {
+        enemy_cannon.shooting_timer.tick(fixed_time.period);
+        if !enemy_cannon.is_alive {
             continue;
         }
         if enemy_cannon.shooting_timer.finished() {
-            let enemy_translation = global_transform.translation().truncate();
+            let pirate_transform = pirates_query.get(enemy_pirate.get()).unwrap();
+            let enemy_translation = pirate_transform
+                .mul_transform(*cannon_transform)
+                .translation
+                .truncate();
             commands
-                .spawn(SpriteBundle {
-                    texture: textures.bullet.clone(),
+                .spawn(SpatialBundle {
                     transform: Transform::from_translation(Vec3::new(
                         enemy_translation.x,
                         enemy_translation.y,
@@ -360,93 +367,55 @@ fn pirates_shoot_at_player(
                     )),
                     ..Default::default()
                 })
-                .insert(Bullet::new(enemy_pirate.get()))
+                .insert(Bullet::new(
+                    enemy_pirate.get(),
+                    balance.pirates.bullet_damage,
+                ))
+                .insert(
+                    Lifetime::behind_camera(OUT_OF_SIGHT)
+                        .with_max_range(BULLET_RANGE)
+                        .with_max_age(BULLET_MAX_AGE),
+                )
                 .insert(Movement {
                     vector: enemy_cannon.vector,
-                    speed: 300.0,
-                    ..Default::default()
+                    speed: balance.pirates.bullet_speed
+                        * level.scale(balance.difficulty.bullet_speed_growth),
                 });
-            audio
-                .play(audio_assets.bullet_fire.clone())
-                .with_volume(0.3);
+            sound_events.send(SoundEvent::EnemyShot);
         }
     }
 }
 
 fn detect_killed_enemies(
-    mut enemies_q: Query<(&mut Enemy, &mut Handle<Image>, &Health)>,
-    textures: Res<TextureAssets>,
+    mut death_events: EventReader<DeathEvent>,
+    mut enemies_q: Query<&mut Enemy>,
     mut game_score: ResMut<GameScore>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    balance: Res<Balance>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
-    for (mut enemy, mut handle, health) in enemies_q.iter_mut() {
-        if enemy.is_alive == false {
-            continue;
-        }
-        if health.health_amount <= 0 {
+    for death in death_events.iter() {
+        if let Ok(mut enemy) = enemies_q.get_mut(death.target) {
             enemy.is_alive = false;
-            *handle = textures.enemy_cannon_crashed.clone();
-            game_score.score += 10;
-            audio.play(audio_assets.boat_crash.clone()).with_volume(0.1);
+            game_score.score += balance.side_cannons.score;
+            sound_events.send(SoundEvent::EnemyCrashed);
         }
     }
 }
 
 fn detect_killed_pirates(
-    mut enemies_q: Query<(&Children, &Health), With<EnemyPirate>>,
+    mut death_events: EventReader<DeathEvent>,
+    enemies_q: Query<&Children, With<EnemyPirate>>,
     mut children_q: Query<&mut EnemyPirateCannon>,
     mut game_score: ResMut<GameScore>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    balance: Res<Balance>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
-    for (children, health) in enemies_q.iter_mut() {
-        let cannon_entity = children[0];
-        let mut cannon = children_q.get_mut(cannon_entity).unwrap();
-        if cannon.is_alive == false {
-            continue;
-        }
-        if health.health_amount <= 0 {
+    for death in death_events.iter() {
+        if let Ok(children) = enemies_q.get(death.target) {
+            let mut cannon = children_q.get_mut(children[0]).unwrap();
             cannon.is_alive = false;
-            game_score.score += 30;
-            audio.play(audio_assets.boat_crash.clone()).with_volume(0.1);
-        }
-    }
-}
-
-fn increase_difficulty_medium(mut spawn_timers: ResMut<EnemySpawnTimers>) {
-    spawn_timers
-        .pirate_ships
-        .push(Timer::new(Duration::from_secs(5), TimerMode::Repeating));
-}
-
-fn increase_difficulty_hard(mut spawn_timers: ResMut<EnemySpawnTimers>) {
-    spawn_timers
-        .pirate_ships
-        .push(Timer::new(Duration::from_secs(5), TimerMode::Repeating));
-    spawn_timers
-        .side_cannons
-        .push(Timer::new(Duration::from_secs(5), TimerMode::Repeating));
-}
-
-fn display_pirate_damage(
-    mut pirate_q: Query<(&mut Handle<Image>, &Health), With<EnemyPirate>>,
-    textures: Res<TextureAssets>,
-) {
-    for (mut handle, health) in pirate_q.iter_mut() {
-        let health_percentage = health.health_amount as f32 / health.max_health as f32 * 100.;
-        let next_texture = if health_percentage <= 0. {
-            &textures.enemy_pirate1_crashed
-        } else if health_percentage <= 40. {
-            &textures.enemy_pirate1_dmg2
-        } else if health_percentage <= 70. {
-            &textures.enemy_pirate1_dmg1
-        } else {
-            &textures.enemy_pirate1
-        };
-
-        if *handle != *next_texture {
-            *handle = next_texture.clone();
+            game_score.score += balance.pirates.score;
+            sound_events.send(SoundEvent::EnemyCrashed);
         }
     }
 }
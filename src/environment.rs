@@ -1,10 +1,13 @@
+use std::collections::VecDeque;
 use std::f32::consts::PI;
 
-use crate::loading::TextureAssets;
-use crate::menu::MainCamera;
-use crate::GameState;
+use crate::balance::Balance;
+use crate::camera::MainCamera;
+use crate::collision::Shape;
+use crate::random::{GameRng, RngStream};
+use crate::{GameState, SimulationSet};
 use bevy::prelude::*;
-use rand::seq::SliceRandom;
+use rand::Rng;
 
 pub const MAP_WIDTH: f32 = 512.;
 pub const MAP_HEIGHT: f32 = 576.;
@@ -13,7 +16,7 @@ pub struct EnvironmentPlugin;
 
 #[derive(Component)]
 pub struct Collidable {
-    pub size: Vec2,
+    pub shape: Shape,
     pub damage: i32,
     pub is_alive: bool,
 }
@@ -22,157 +25,177 @@ pub struct Collidable {
 pub struct LandTile;
 
 #[derive(Component)]
-pub struct BorderTile;
+pub struct BorderTile {
+    /// Which of the border textures is used for this tile
+    pub variant: usize,
+}
+
+pub const BORDER_TILE_VARIANTS: usize = 4;
 
 #[derive(Component)]
 pub struct WaterTile;
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct MapObject {
-    water_top: f32,
-    border_top: f32,
-    land_top: f32,
+    /// Where the last generated row of tiles is
+    top: f32,
+    /// Generated chunks still around, the oldest first
+    chunks: VecDeque<MapChunk>,
 }
 
-impl Default for MapObject {
-    fn default() -> Self {
-        MapObject {
-            water_top: 0.,
-            border_top: 0.,
-            land_top: 0.,
-        }
-    }
+struct MapChunk {
+    top: f32,
+    tiles: Vec<Entity>,
 }
 
 pub const LAND_TILE_SIZE: f32 = 64.;
 pub const WATER_TILE_SIZE: f32 = 64.;
 pub const BORDER_TILE_HEIGHT: f32 = 64.;
 
-/// This plugin handles player related stuff like movement
-/// Player logic is only active during the State `GameState::Playing`
+/// Every row of the map is one tile high
+const ROW_HEIGHT: f32 = 64.;
+/// The river is generated and removed in chunks of this many rows
+pub const CHUNK_ROWS: usize = 8;
+/// How far ahead of the camera the river has to be generated already
+const GENERATE_AHEAD: f32 = 800.;
+/// Chunks that are this far behind the camera are not visible anymore and get despawned
+const DESPAWN_BEHIND: f32 = 600.;
+
+/// This plugin generates the river and its banks ahead of the camera
+/// and removes them again once the camera has left them behind,
+/// so the number of tiles stays the same no matter how far the boat gets.
+/// Environment logic is only active during the State `GameState::Playing`
 impl Plugin for EnvironmentPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MapObject>()
-            .add_system(spawn_water.in_set(OnUpdate(GameState::Playing)))
-            .add_system(spawn_border.in_set(OnUpdate(GameState::Playing)))
-            .add_system(spawn_land.in_set(OnUpdate(GameState::Playing)))
+            .add_systems(
+                (spawn_chunks, despawn_chunks_behind)
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
             .add_system(despawn_environment.in_schedule(OnEnter(GameState::Restart)));
     }
 }
 
-fn spawn_water(
+fn spawn_chunks(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
     camera_query: Query<&Transform, With<MainCamera>>,
     mut map_object: ResMut<MapObject>,
+    mut game_rng: ResMut<GameRng>,
+    balance: Res<Balance>,
 ) {
     let camera_transform = camera_query.get_single().unwrap();
-    let y_where_water_should_be_generated = camera_transform.translation.y + 800.;
-
-    if map_object.water_top > y_where_water_should_be_generated {
-        return;
+    let y_where_map_should_be_generated = camera_transform.translation.y + GENERATE_AHEAD;
+
+    let rng = game_rng.stream(RngStream::Environment);
+    while map_object.top <= y_where_map_should_be_generated {
+        let mut tiles = vec![];
+        for _ in 0..CHUNK_ROWS {
+            let row_y = map_object.top + ROW_HEIGHT;
+            spawn_water(&mut commands, row_y, &mut tiles);
+            spawn_border(&mut commands, row_y, rng, &mut tiles);
+            spawn_land(&mut commands, row_y, balance.river.land_damage, &mut tiles);
+            map_object.top = row_y;
+        }
+        let top = map_object.top + ROW_HEIGHT / 2.;
+        map_object.chunks.push_back(MapChunk { top, tiles });
     }
-
-    let water_tile_x_positions = (0..(MAP_WIDTH / WATER_TILE_SIZE) as u32)
-        .map(|x| x as f32 * WATER_TILE_SIZE + WATER_TILE_SIZE / 2.);
-    let water_tile_y_position = map_object.water_top + WATER_TILE_SIZE;
-
-    let map_matrix = water_tile_x_positions.map(move |x| (x, water_tile_y_position));
-    map_matrix.for_each(|(x, y)| {
-        commands
-            .spawn(SpriteBundle {
-                texture: textures.water_tile.clone(),
-                transform: Transform::from_translation(Vec3::new(x as f32, y as f32, 1.)),
-                ..Default::default()
-            })
-            .insert(WaterTile);
-    });
-    map_object.water_top = water_tile_y_position;
 }
 
-fn spawn_border(
+fn despawn_chunks_behind(
     mut commands: Commands,
-    textures: Res<TextureAssets>,
     camera_query: Query<&Transform, With<MainCamera>>,
     mut map_object: ResMut<MapObject>,
 ) {
     let camera_transform = camera_query.get_single().unwrap();
-    let y_where_border_should_be_generated = camera_transform.translation.y + 800.;
+    let y_where_map_can_be_removed = camera_transform.translation.y - DESPAWN_BEHIND;
+
+    while map_object
+        .chunks
+        .front()
+        .is_some_and(|chunk| chunk.top < y_where_map_can_be_removed)
+    {
+        let chunk = map_object.chunks.pop_front().unwrap();
+        for tile in chunk.tiles {
+            commands.entity(tile).despawn_recursive();
+        }
+    }
+}
 
-    if map_object.border_top > y_where_border_should_be_generated {
-        return;
+fn spawn_water(commands: &mut Commands, row_y: f32, tiles: &mut Vec<Entity>) {
+    let water_tile_x_positions = (0..(MAP_WIDTH / WATER_TILE_SIZE) as u32)
+        .map(|x| x as f32 * WATER_TILE_SIZE + WATER_TILE_SIZE / 2.);
+
+    for x in water_tile_x_positions {
+        let tile = commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(x, row_y, 1.)),
+                ..Default::default()
+            })
+            .insert(WaterTile)
+            .id();
+        tiles.push(tile);
     }
-    let border_tiles = [
-        textures.border_tile1.clone(),
-        textures.border_tile2.clone(),
-        textures.border_tile3.clone(),
-        textures.border_tile4.clone(),
-    ];
-    let mut rng = rand::thread_rng();
+}
+
+fn spawn_border(commands: &mut Commands, row_y: f32, rng: &mut impl Rng, tiles: &mut Vec<Entity>) {
     // Left side
-    let border_y = map_object.border_top + BORDER_TILE_HEIGHT;
-    commands
-        .spawn(SpriteBundle {
-            texture: border_tiles.choose(&mut rng).unwrap().clone(),
+    let left = commands
+        .spawn(SpatialBundle {
             transform: Transform::from_translation(Vec3::new(
                 0. + BORDER_TILE_HEIGHT / 2.,
-                border_y,
+                row_y,
                 1.1,
             ))
             .with_rotation(Quat::from_rotation_z(PI)),
             ..Default::default()
         })
-        .insert(BorderTile);
+        .insert(BorderTile {
+            variant: rng.gen_range(0..BORDER_TILE_VARIANTS),
+        })
+        .id();
 
     // Right side
-    commands
-        .spawn(SpriteBundle {
-            texture: border_tiles.choose(&mut rng).unwrap().clone(),
+    let right = commands
+        .spawn(SpatialBundle {
             transform: Transform::from_translation(Vec3::new(
                 MAP_WIDTH - BORDER_TILE_HEIGHT / 2.,
-                border_y,
+                row_y,
                 1.1,
             )),
             ..Default::default()
         })
-        .insert(BorderTile);
+        .insert(BorderTile {
+            variant: rng.gen_range(0..BORDER_TILE_VARIANTS),
+        })
+        .id();
 
-    map_object.border_top = border_y;
+    tiles.extend([left, right]);
 }
 
-fn spawn_land(
-    mut commands: Commands,
-    textures: Res<TextureAssets>,
-    camera_query: Query<&Transform, With<MainCamera>>,
-    mut map_object: ResMut<MapObject>,
-) {
-    let camera_transform = camera_query.get_single().unwrap();
-    let y_where_land_should_be_generated = camera_transform.translation.y + 800.;
-
-    if map_object.land_top > y_where_land_should_be_generated {
-        return;
-    }
-    let land_y = map_object.land_top + LAND_TILE_SIZE;
+fn spawn_land(commands: &mut Commands, row_y: f32, damage: i32, tiles: &mut Vec<Entity>) {
     for tile_mid in [
         0. - 3. * LAND_TILE_SIZE / 2.,
         0. - LAND_TILE_SIZE / 2.,
         MAP_WIDTH + LAND_TILE_SIZE / 2.,
         MAP_WIDTH + 3. * LAND_TILE_SIZE / 2.,
     ] {
-        commands
-            .spawn(SpriteBundle {
-                texture: textures.land_tile.clone(),
-                transform: Transform::from_translation(Vec3::new(tile_mid, land_y, 1.1)),
+        let tile = commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(tile_mid, row_y, 1.1)),
                 ..Default::default()
             })
             .insert(LandTile)
             .insert(Collidable {
-                size: Vec2::new(LAND_TILE_SIZE, LAND_TILE_SIZE),
-                damage: 100,
+                shape: Shape::Rectangle {
+                    size: Vec2::new(LAND_TILE_SIZE, LAND_TILE_SIZE),
+                },
+                damage,
                 is_alive: true,
-            });
+            })
+            .id();
+        tiles.push(tile);
     }
-    map_object.land_top = land_y;
 }
 
 fn despawn_environment(
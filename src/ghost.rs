@@ -0,0 +1,214 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    environment::MAP_HEIGHT, player::Player, replay::is_playing_back, score::GameScore,
+    simulation_running, storage, GameState, SimulationSet,
+};
+
+pub struct GhostPlugin;
+
+/// This plugin races the player against the best run played on this machine.
+/// The boat's position is sampled while playing and the run that got the furthest is saved,
+/// the next runs show it as a translucent ghost boat that can not be hit and hits nothing.
+/// Replays that are played back are neither saved nor raced against
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BestTrajectory(Trajectory::load()))
+            .init_resource::<CurrentTrajectory>()
+            .init_resource::<GhostSettings>()
+            .init_resource::<GhostGap>()
+            .add_system(start_trajectory.in_schedule(OnEnter(GameState::Init)))
+            .add_system(
+                spawn_ghost
+                    .run_if(not(is_playing_back))
+                    .in_schedule(OnEnter(GameState::Init)),
+            )
+            .add_system(
+                sample_trajectory
+                    .after(SimulationSet::Logic)
+                    .run_if(simulation_running)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(move_ghost.in_set(OnUpdate(GameState::Playing)))
+            .add_system(
+                save_best_trajectory
+                    .run_if(not(is_playing_back))
+                    .in_schedule(OnEnter(GameState::End)),
+            )
+            .add_system(despawn_ghost.in_schedule(OnEnter(GameState::Restart)));
+    }
+}
+
+const STORAGE_NAME: &str = "ghost";
+
+/// The position of the boat is saved every this many simulation ticks
+const SAMPLE_TICKS: u32 = 6;
+
+/// Path of the boat during a run
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Trajectory {
+    pub distance: f32,
+    /// Position and rotation around z of the boat every [`SAMPLE_TICKS`] ticks, starting after the first tick
+    pub samples: Vec<(f32, f32, f32)>,
+}
+
+impl Trajectory {
+    fn load() -> Option<Trajectory> {
+        let content = storage::read(STORAGE_NAME)?;
+        ron::from_str(&content)
+            .map_err(|error| warn!("Ignoring unreadable ghost: {}", error))
+            .ok()
+    }
+
+    fn save(&self) {
+        let result = ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|content| storage::write(STORAGE_NAME, &content));
+        if let Err(error) = result {
+            error!("Could not save ghost: {}", error);
+        }
+    }
+
+    /// Where the boat was at the given tick, in between samples, or none after the run ended
+    fn at(&self, tick: f32) -> Option<(Vec2, f32)> {
+        let index = ((tick - 1.) / SAMPLE_TICKS as f32).max(0.);
+        let (x, y, angle) = *self.samples.get(index as usize)?;
+        let (next_x, next_y, next_angle) = *self.samples.get(index as usize + 1)?;
+        let alpha = index.fract();
+        Some((
+            Vec2::new(x, y).lerp(Vec2::new(next_x, next_y), alpha),
+            angle + (next_angle - angle) * alpha,
+        ))
+    }
+}
+
+/// The run that got the furthest so far
+#[derive(Resource)]
+struct BestTrajectory(Option<Trajectory>);
+
+#[derive(Resource, Default)]
+struct CurrentTrajectory {
+    ticks: u32,
+    trajectory: Trajectory,
+}
+
+/// Chosen in the menu, whether the next runs race against the ghost
+#[derive(Resource)]
+pub struct GhostSettings {
+    pub enabled: bool,
+}
+
+impl Default for GhostSettings {
+    fn default() -> Self {
+        GhostSettings { enabled: true }
+    }
+}
+
+/// How many meters the player is ahead of the ghost, behind when negative.
+/// Not set when there is no ghost to race
+#[derive(Resource, Default)]
+pub struct GhostGap(pub Option<f32>);
+
+/// Boat of the best run, it follows the saved [`Trajectory`] and is not part of the simulation
+#[derive(Component)]
+pub struct Ghost;
+
+fn start_trajectory(mut current: ResMut<CurrentTrajectory>, mut gap: ResMut<GhostGap>) {
+    *current = CurrentTrajectory::default();
+    gap.0 = None;
+}
+
+fn spawn_ghost(mut commands: Commands, best: Res<BestTrajectory>, settings: Res<GhostSettings>) {
+    let Some(best) = &best.0 else {
+        return;
+    };
+    if !settings.enabled || best.samples.is_empty() {
+        return;
+    }
+    let (x, y, angle) = best.samples[0];
+    commands
+        .spawn(SpatialBundle {
+            // Right below the player
+            transform: Transform::from_translation(Vec3::new(x, y, 4.9))
+                .with_rotation(Quat::from_rotation_z(angle)),
+            ..default()
+        })
+        .insert(Ghost);
+}
+
+fn sample_trajectory(
+    mut current: ResMut<CurrentTrajectory>,
+    player_q: Query<&Transform, With<Player>>,
+    game_score: Res<GameScore>,
+) {
+    current.ticks += 1;
+    if !(current.ticks - 1).is_multiple_of(SAMPLE_TICKS) {
+        return;
+    }
+    let Ok(transform) = player_q.get_single() else {
+        return;
+    };
+    let angle = transform.rotation.to_euler(EulerRot::YXZ).2;
+    current.trajectory.distance = game_score.distance_traveled;
+    current
+        .trajectory
+        .samples
+        .push((transform.translation.x, transform.translation.y, angle));
+}
+
+fn move_ghost(
+    mut ghost_q: Query<(&mut Transform, &mut Visibility), With<Ghost>>,
+    player_q: Query<&Transform, (With<Player>, Without<Ghost>)>,
+    best: Res<BestTrajectory>,
+    game_score: Res<GameScore>,
+    fixed_time: Res<FixedTime>,
+    mut gap: ResMut<GhostGap>,
+) {
+    let (Ok((mut transform, mut visibility)), Some(best)) = (ghost_q.get_single_mut(), &best.0)
+    else {
+        return;
+    };
+    let Ok(player_transform) = player_q.get_single() else {
+        return;
+    };
+    // Same moment the interpolated player is shown at, in between the last two ticks
+    let period = fixed_time.period.as_secs_f32();
+    let tick = game_score.elapsed_time.elapsed_secs() / period - 1.
+        + (fixed_time.accumulated().as_secs_f32() / period).clamp(0., 1.);
+    let ghost_y = match best.at(tick) {
+        Some((position, angle)) => {
+            transform.translation = position.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(angle);
+            *visibility = Visibility::Inherited;
+            position.y
+        }
+        // The best run ended here
+        None => {
+            *visibility = Visibility::Hidden;
+            best.distance * 16. + MAP_HEIGHT / 2.
+        }
+    };
+    gap.0 = Some((player_transform.translation.y - ghost_y) / 16.);
+}
+
+fn save_best_trajectory(
+    mut current: ResMut<CurrentTrajectory>,
+    mut best: ResMut<BestTrajectory>,
+    game_score: Res<GameScore>,
+) {
+    let best_distance = best.0.as_ref().map_or(0., |best| best.distance);
+    if game_score.distance_traveled <= best_distance {
+        return;
+    }
+    let mut trajectory = std::mem::take(&mut current.trajectory);
+    trajectory.distance = game_score.distance_traveled;
+    trajectory.save();
+    best.0 = Some(trajectory);
+}
+
+fn despawn_ghost(mut commands: Commands, ghost_q: Query<Entity, With<Ghost>>) {
+    for entity in ghost_q.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,229 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::MainCamera,
+    enemy::{Enemy, EnemyPirate, EnemyPirateCannon},
+    environment::{BorderTile, Collidable, LandTile, WaterTile},
+    ghost::Ghost,
+    health::{Bullet, Health, Invulnerability},
+    loading::TextureAssets,
+    obstacle::ObstacleTile,
+    player::{Player, PlayerCannon},
+    power_up::{PowerUp, PowerUpBarrel, PowerUpType},
+    GameState,
+};
+
+pub struct GraphicsPlugin;
+
+/// This plugin gives the simulated entities their looks.
+/// The simulation spawns bare transforms, sprites are attached here once the textures are loaded.
+/// Systems in this plugin never change the state of the game
+impl Plugin for GraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(attach_camera.in_base_set(CoreSet::PostUpdate))
+            .add_system(
+                attach_sprites
+                    .in_base_set(CoreSet::PostUpdate)
+                    .run_if(resource_exists::<TextureAssets>()),
+            )
+            .add_system(display_boat_damage.in_set(OnUpdate(GameState::Playing)))
+            .add_system(display_pirate_damage.in_set(OnUpdate(GameState::Playing)))
+            .add_system(display_dead_obstacles.in_set(OnUpdate(GameState::Playing)))
+            .add_system(display_dead_enemies.in_set(OnUpdate(GameState::Playing)))
+            .add_system(blink_invulnerable);
+    }
+}
+
+/// Invulnerable entities are hidden and shown again this many milliseconds at a time
+const BLINK_INTERVAL_MS: u128 = 100;
+
+/// Tint of the boat of the best run, see-through so it does not hide what is under it
+const GHOST_COLOR: Color = Color::rgba(1., 1., 1., 0.4);
+
+fn attach_camera(mut commands: Commands, camera_q: Query<(Entity, &Transform), Added<MainCamera>>) {
+    for (entity, transform) in camera_q.iter() {
+        commands.entity(entity).insert(Camera2dBundle {
+            transform: *transform,
+            ..default()
+        });
+    }
+}
+
+fn attach_sprites(
+    mut commands: Commands,
+    textures: Res<TextureAssets>,
+    new_entities_q: Query<
+        (
+            Entity,
+            Option<&Player>,
+            Option<&PlayerCannon>,
+            Option<&Bullet>,
+            Option<&ObstacleTile>,
+            Option<&Enemy>,
+            Option<&EnemyPirate>,
+            Option<&EnemyPirateCannon>,
+            Option<&PowerUpBarrel>,
+            Option<&PowerUp>,
+            Option<&WaterTile>,
+            Option<&BorderTile>,
+            Option<&LandTile>,
+            Option<&Ghost>,
+        ),
+        Added<Transform>,
+    >,
+) {
+    let obstacles = [
+        &textures.obstacle_rock1,
+        &textures.obstacle_rock2,
+        &textures.obstacle_rock3,
+        &textures.obstacle_wood1,
+        &textures.obstacle_wood2,
+        &textures.obstacle_wood3,
+    ];
+    let border_tiles = [
+        &textures.border_tile1,
+        &textures.border_tile2,
+        &textures.border_tile3,
+        &textures.border_tile4,
+    ];
+    for (
+        entity,
+        player,
+        player_cannon,
+        bullet,
+        obstacle,
+        enemy,
+        pirate,
+        pirate_cannon,
+        barrel,
+        power_up,
+        water,
+        border,
+        land,
+        ghost,
+    ) in new_entities_q.iter()
+    {
+        let texture = if player.is_some() || ghost.is_some() {
+            &textures.boat
+        } else if player_cannon.is_some() || pirate_cannon.is_some() {
+            &textures.boat_cannon
+        } else if bullet.is_some() {
+            &textures.bullet
+        } else if let Some(obstacle) = obstacle {
+            // Variants beyond the textures we have reuse them
+            obstacles[obstacle.variant % obstacles.len()]
+        } else if enemy.is_some() {
+            &textures.enemy_cannon
+        } else if pirate.is_some() {
+            &textures.enemy_pirate1
+        } else if barrel.is_some() {
+            &textures.barrel
+        } else if let Some(power_up) = power_up {
+            match power_up.kind {
+                PowerUpType::Repair => &textures.power_up_health,
+                PowerUpType::Weapon => &textures.power_up_weapon,
+            }
+        } else if water.is_some() {
+            &textures.water_tile
+        } else if let Some(border) = border {
+            border_tiles[border.variant]
+        } else if land.is_some() {
+            &textures.land_tile
+        } else {
+            continue;
+        };
+        let sprite = if ghost.is_some() {
+            Sprite {
+                color: GHOST_COLOR,
+                ..default()
+            }
+        } else {
+            Sprite::default()
+        };
+        commands.entity(entity).insert((sprite, texture.clone()));
+    }
+}
+
+fn display_boat_damage(
+    mut player_q: Query<(&mut Handle<Image>, &Health), (With<Player>, Changed<Health>)>,
+    textures: Res<TextureAssets>,
+) {
+    for (mut handle, health) in player_q.iter_mut() {
+        let health_percentage = health.health_amount as f32 / health.max_health as f32 * 100.;
+        let next_texture = if health_percentage <= 0. {
+            &textures.boat_crashed
+        } else if health_percentage <= 33. {
+            &textures.boat_dmg2
+        } else if health_percentage <= 66. {
+            &textures.boat_dmg1
+        } else {
+            &textures.boat
+        };
+
+        if *handle != *next_texture {
+            *handle = next_texture.clone();
+        }
+    }
+}
+
+fn display_pirate_damage(
+    mut pirate_q: Query<(&mut Handle<Image>, &Health), (With<EnemyPirate>, Changed<Health>)>,
+    textures: Res<TextureAssets>,
+) {
+    for (mut handle, health) in pirate_q.iter_mut() {
+        let health_percentage = health.health_amount as f32 / health.max_health as f32 * 100.;
+        let next_texture = if health_percentage <= 0. {
+            &textures.enemy_pirate1_crashed
+        } else if health_percentage <= 40. {
+            &textures.enemy_pirate1_dmg2
+        } else if health_percentage <= 70. {
+            &textures.enemy_pirate1_dmg1
+        } else {
+            &textures.enemy_pirate1
+        };
+
+        if *handle != *next_texture {
+            *handle = next_texture.clone();
+        }
+    }
+}
+
+fn display_dead_obstacles(
+    mut obstacles_q: Query<
+        (&Collidable, &mut Handle<Image>),
+        (With<ObstacleTile>, Changed<Collidable>),
+    >,
+    textures: Res<TextureAssets>,
+) {
+    for (obstacle, mut handle) in obstacles_q.iter_mut() {
+        if !obstacle.is_alive {
+            *handle = textures.obstacle_wood_dead.clone();
+        }
+    }
+}
+
+fn display_dead_enemies(
+    mut enemies_q: Query<(&Enemy, &mut Handle<Image>), Changed<Enemy>>,
+    textures: Res<TextureAssets>,
+) {
+    for (enemy, mut handle) in enemies_q.iter_mut() {
+        if !enemy.is_alive {
+            *handle = textures.enemy_cannon_crashed.clone();
+        }
+    }
+}
+
+fn blink_invulnerable(mut invulnerable_q: Query<(&Invulnerability, &mut Visibility)>) {
+    for (invulnerability, mut visibility) in invulnerable_q.iter_mut() {
+        let is_hidden = invulnerability.is_active()
+            && (invulnerability.elapsed().as_millis() / BLINK_INTERVAL_MS).is_multiple_of(2);
+        let next_visibility = if is_hidden {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        if *visibility != next_visibility {
+            *visibility = next_visibility;
+        }
+    }
+}
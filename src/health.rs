@@ -1,8 +1,23 @@
-use bevy::{prelude::*, sprite::collide_aabb::collide};
-use bevy_kira_audio::{Audio, AudioControl};
+use std::time::Duration;
 
-use crate::{environment::MAP_WIDTH, loading::AudioAssets, GameState};
+use bevy::prelude::*;
+use serde::Deserialize;
 
+use crate::{
+    audio::SoundEvent,
+    balance::Balance,
+    collision::{Shape, SpatialGrid},
+    player::Player,
+    score::GameScore,
+    GameState, SimulationSet,
+};
+
+/// How far a bullet flies before it falls into the river
+pub const BULLET_RANGE: f32 = 700.;
+/// Bullets still flying after this long are removed as well, however slow they are
+pub const BULLET_MAX_AGE: Duration = Duration::from_secs(4);
+
+#[derive(Deserialize, Clone, Copy, Debug)]
 pub enum Mass {
     Wood,
     Rock,
@@ -12,7 +27,7 @@ pub enum Mass {
 pub struct Health {
     pub max_health: i32,
     pub health_amount: i32,
-    pub size: Vec2,
+    pub shape: Shape,
     pub immune_to_bullets: bool,
     pub mass: Mass,
 }
@@ -21,73 +36,269 @@ pub struct Health {
 pub struct Bullet {
     pub shooter: Entity,
     pub damage: i32,
-    pub size: Vec2,
+    pub shape: Shape,
 }
 
 impl Bullet {
-    pub fn new(shooter: Entity) -> Self {
+    pub fn new(shooter: Entity, damage: i32) -> Self {
         Bullet {
             shooter,
-            damage: 1,
-            size: Vec2::new(5., 5.),
+            damage,
+            shape: Shape::Circle { radius: 2.5 },
         }
     }
 }
 
+/// Ignores all damage for a while after every hit that did not kill
+#[derive(Component)]
+pub struct Invulnerability {
+    timer: Timer,
+}
+
+impl Invulnerability {
+    pub fn new(duration: Duration) -> Self {
+        let mut timer = Timer::new(duration, TimerMode::Once);
+        // Nothing has hit yet
+        timer.tick(duration);
+        Invulnerability { timer }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.timer.finished()
+    }
+
+    /// Time since the hit that made the entity invulnerable
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+}
+
+/// Pushes the entity away from whatever it collides with
+#[derive(Component)]
+pub struct Knockback {
+    /// Speed of the push right after the collision, in pixels per second
+    pub speed: f32,
+    velocity: Vec2,
+}
+
+impl Knockback {
+    pub fn new(speed: f32) -> Self {
+        Knockback {
+            speed,
+            velocity: Vec2::ZERO,
+        }
+    }
+}
+
+/// How quickly the push of a [`Knockback`] fades away, in pixels per second squared
+const KNOCKBACK_DECELERATION: f32 = 900.;
+
+/// What took the health away
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageKind {
+    Bullet,
+    Collision,
+}
+
+/// Sent by whatever hits something, the [`Health`] of the target is only changed by [`HealthPlugin`].
+/// The amount is before the damage multipliers of the difficulty preset
+pub struct DamageEvent {
+    /// Who shot the bullet or what was rammed
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    pub kind: DamageKind,
+}
+
+/// The health of the target ran out, sent once for every entity
+pub struct DeathEvent {
+    /// Source of the damage that killed the target
+    pub source: Entity,
+    pub target: Entity,
+    pub kind: DamageKind,
+}
+
+/// Sent for every [`DamageEvent`] that actually took health, with the amount taken
+/// after invulnerability and the damage multipliers of the difficulty preset
+pub struct HurtEvent {
+    pub source: Entity,
+    pub target: Entity,
+    pub amount: i32,
+    pub kind: DamageKind,
+}
+
 pub struct HealthPlugin;
 
+/// This plugin finds bullet hits and is the only place where damage is taken from [`Health`].
+/// Everything that cares about hits and deaths reads the [`DamageEvent`]s and [`DeathEvent`]s.
+/// Collisions also push entities with [`Knockback`] away, whether they took damage or not
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(detect_bullet_collisions.in_set(OnUpdate(GameState::Playing)))
-            .add_system(despawn_blind_bullets.in_set(OnUpdate(GameState::Playing)))
+        app.add_event::<DamageEvent>()
+            .add_event::<HurtEvent>()
+            .add_event::<DeathEvent>()
+            .add_system(
+                apply_knockback
+                    .in_set(SimulationSet::Movement)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                detect_bullet_collisions
+                    .in_set(SimulationSet::Collision)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (tick_invulnerability, apply_damage, start_knockback)
+                    .chain()
+                    .in_set(SimulationSet::Damage)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
             .add_system(despawn_bullets.in_schedule(OnEnter(GameState::Restart)));
     }
 }
 
-fn detect_bullet_collisions(
+pub fn detect_bullet_collisions(
     mut commands: Commands,
     bullets_query: Query<(Entity, &Transform, &Bullet)>,
-    mut health_query: Query<(&Transform, &mut Health, Entity)>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    health_query: Query<(&Transform, &Health)>,
+    grid: Res<SpatialGrid>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
     for (bullet_entity, bullet_transform, bullet) in bullets_query.iter() {
-        for (health_transform, mut health, entity) in health_query.iter_mut() {
+        let nearby = grid.query(
+            bullet_transform.translation.truncate(),
+            bullet.shape.bounds(bullet_transform),
+        );
+        for entity in nearby {
+            let Ok((health_transform, health)) = health_query.get(entity) else {
+                continue;
+            };
             if bullet.shooter == entity || health.immune_to_bullets {
                 continue;
             }
-            let collision = collide(
-                bullet_transform.translation,
-                bullet.size,
-                health_transform.translation,
-                health.size,
-            );
-            if Option::is_some(&collision) {
+            if bullet
+                .shape
+                .intersects(bullet_transform, &health.shape, health_transform)
+            {
                 commands.entity(bullet_entity).despawn();
-                health.health_amount = health.health_amount - bullet.damage;
-                match health.mass {
-                    Mass::Wood => {
-                        audio.play(audio_assets.bullet_hit.clone()).with_volume(0.4);
-                    }
-                    Mass::Rock => {
-                        audio
-                            .play(audio_assets.bullet_hit_rock.clone())
-                            .with_volume(0.8);
-                    }
-                }
+                damage_events.send(DamageEvent {
+                    source: bullet.shooter,
+                    target: entity,
+                    amount: bullet.damage,
+                    kind: DamageKind::Bullet,
+                });
+                // Spent on the first thing it hits, even where several overlap
+                break;
             }
         }
     }
 }
 
-fn despawn_blind_bullets(
-    mut commands: Commands,
-    bullet_query: Query<(Entity, &Transform), With<Bullet>>,
+fn tick_invulnerability(
+    mut invulnerability_q: Query<&mut Invulnerability>,
+    fixed_time: Res<FixedTime>,
+) {
+    for mut invulnerability in invulnerability_q.iter_mut() {
+        invulnerability.timer.tick(fixed_time.period);
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut health_query: Query<(&mut Health, Option<&mut Invulnerability>)>,
+    player_q: Query<(), With<Player>>,
+    game_score: Res<GameScore>,
+    balance: Res<Balance>,
+    mut hurt_events: EventWriter<HurtEvent>,
+    mut death_events: EventWriter<DeathEvent>,
+    mut sound_events: EventWriter<SoundEvent>,
+) {
+    let preset = balance.presets.get(game_score.preset);
+    for damage in damage_events.iter() {
+        let Ok((mut health, invulnerability)) = health_query.get_mut(damage.target) else {
+            continue;
+        };
+        if invulnerability
+            .as_ref()
+            .is_some_and(|invulnerability| invulnerability.is_active())
+        {
+            continue;
+        }
+        let multiplier = if player_q.contains(damage.target) {
+            preset.damage_taken_multiplier
+        } else if player_q.contains(damage.source) {
+            preset.damage_dealt_multiplier
+        } else {
+            1.
+        };
+        // Rounded up, a hit always costs at least one point of health
+        let amount = (damage.amount as f32 * multiplier).ceil() as i32;
+        let was_alive = health.health_amount > 0;
+        health.health_amount -= amount;
+        if let Some(mut invulnerability) = invulnerability {
+            if amount > 0 && health.health_amount > 0 {
+                invulnerability.timer.reset();
+            }
+        }
+        if amount > 0 {
+            hurt_events.send(HurtEvent {
+                source: damage.source,
+                target: damage.target,
+                amount,
+                kind: damage.kind,
+            });
+        }
+        if damage.kind == DamageKind::Bullet {
+            sound_events.send(match health.mass {
+                Mass::Wood => SoundEvent::BulletHitWood,
+                Mass::Rock => SoundEvent::BulletHitRock,
+            });
+        }
+        if was_alive && health.health_amount <= 0 {
+            death_events.send(DeathEvent {
+                source: damage.source,
+                target: damage.target,
+                kind: damage.kind,
+            });
+        }
+    }
+}
+
+/// Pushes the target away from the source of every collision that hurts
+fn start_knockback(
+    mut damage_events: EventReader<DamageEvent>,
+    mut knockback_q: Query<(&Transform, &mut Knockback)>,
+    transform_q: Query<&Transform>,
 ) {
-    for (bullet_entity, transform) in bullet_query.iter() {
-        if transform.translation.x < -100. || transform.translation.x > MAP_WIDTH + 100. {
-            commands.entity(bullet_entity).despawn();
+    for damage in damage_events.iter() {
+        if damage.kind != DamageKind::Collision || damage.amount <= 0 {
+            continue;
         }
+        let Ok((transform, mut knockback)) = knockback_q.get_mut(damage.target) else {
+            continue;
+        };
+        let Ok(source_transform) = transform_q.get(damage.source) else {
+            continue;
+        };
+        let away = (transform.translation - source_transform.translation).truncate();
+        // Straight down the river when the centres are on top of each other
+        let direction = away.try_normalize().unwrap_or(Vec2::NEG_Y);
+        knockback.velocity = direction * knockback.speed;
+    }
+}
+
+pub fn apply_knockback(
+    mut knockback_q: Query<(&mut Transform, &mut Knockback)>,
+    fixed_time: Res<FixedTime>,
+) {
+    let delta = fixed_time.period.as_secs_f32();
+    for (mut transform, mut knockback) in knockback_q.iter_mut() {
+        if knockback.velocity == Vec2::ZERO {
+            continue;
+        }
+        transform.translation += knockback.velocity.extend(0.) * delta;
+        let speed = (knockback.velocity.length() - KNOCKBACK_DECELERATION * delta).max(0.);
+        knockback.velocity = knockback.velocity.normalize_or_zero() * speed;
     }
 }
 
@@ -96,3 +307,47 @@ fn despawn_bullets(mut commands: Commands, bullet_query: Query<Entity, With<Bull
         commands.entity(bullet_entity).despawn();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spawn_target(world: &mut World, position: Vec2) -> Entity {
+        let size = Vec2::new(32., 32.);
+        let target = world
+            .spawn((
+                Transform::from_translation(position.extend(0.)),
+                Health {
+                    max_health: 10,
+                    health_amount: 10,
+                    shape: Shape::Rectangle { size },
+                    immune_to_bullets: false,
+                    mass: Mass::Wood,
+                },
+            ))
+            .id();
+        world
+            .resource_mut::<SpatialGrid>()
+            .insert(target, position, size);
+        target
+    }
+
+    #[test]
+    fn one_bullet_hits_one_target() {
+        let mut world = World::new();
+        world.init_resource::<SpatialGrid>();
+        world.init_resource::<Events<DamageEvent>>();
+        let shooter = world.spawn_empty().id();
+        // Two targets overlapping right where the bullet is
+        spawn_target(&mut world, Vec2::new(100., 100.));
+        spawn_target(&mut world, Vec2::new(104., 100.));
+        world.spawn((Transform::from_xyz(102., 100., 0.), Bullet::new(shooter, 1)));
+
+        let mut schedule = Schedule::new();
+        schedule.add_system(detect_bullet_collisions);
+        schedule.run(&mut world);
+
+        let events = world.resource::<Events<DamageEvent>>();
+        assert_eq!(events.get_reader().iter(events).count(), 1);
+    }
+}
@@ -0,0 +1,349 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    balance::Balance,
+    daily::{DailyChallenge, GameMode},
+    difficulty::DifficultyPreset,
+    loading::FontAssets,
+    random::GameRng,
+    replay::is_playing_back,
+    score::GameScore,
+    storage, GameState,
+};
+
+pub struct HighScoresPlugin;
+
+/// This plugin keeps the best runs played on this machine, classic runs and daily challenges in separate tables.
+/// The daily table only holds the runs of the latest challenge played.
+/// The tables are loaded once at startup and saved again whenever a run makes it in,
+/// replays that are played back never do
+impl Plugin for HighScoresPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HighScores::load())
+            .init_resource::<NewHighScore>()
+            .add_system(forget_new_high_score.in_schedule(OnEnter(GameState::Init)))
+            .add_system(
+                record_high_score
+                    .run_if(not(is_playing_back))
+                    .in_schedule(OnEnter(GameState::End)),
+            );
+    }
+}
+
+/// How many runs the table keeps
+pub const HIGH_SCORE_COUNT: usize = 10;
+
+const CLASSIC_STORAGE_NAME: &str = "high_scores";
+const DAILY_STORAGE_NAME: &str = "daily_scores";
+
+const HIGHLIGHT_COLOR: Color = Color::rgb(1., 0.8, 0.3);
+
+/// One run in the high-score table
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HighScore {
+    pub score: i32,
+    pub distance: f32,
+    /// Duration of the run in seconds
+    pub time: f32,
+    pub preset: DifficultyPreset,
+    pub seed: u64,
+    /// When the run ended, in seconds since the unix epoch
+    pub date: u64,
+}
+
+/// The best runs of one of the modes, the best one first
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct HighScoreTable {
+    /// Day of the daily challenge the runs were played on, in days since the unix epoch
+    #[serde(default)]
+    pub day: Option<u64>,
+    pub entries: Vec<HighScore>,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct HighScores {
+    pub classic: HighScoreTable,
+    pub daily: HighScoreTable,
+}
+
+impl HighScores {
+    fn load() -> HighScores {
+        HighScores {
+            classic: HighScoreTable::load(CLASSIC_STORAGE_NAME),
+            daily: HighScoreTable::load(DAILY_STORAGE_NAME),
+        }
+    }
+
+    /// Runs of the mode, for the daily challenge only the ones of the given day
+    pub fn entries(&self, mode: GameMode, day: u64) -> &[HighScore] {
+        match mode {
+            GameMode::Classic => &self.classic.entries,
+            GameMode::Daily if self.daily.day == Some(day) => &self.daily.entries,
+            GameMode::Daily => &[],
+        }
+    }
+}
+
+/// Position of the last run in the [`HighScores`] of its mode, if it made it in
+#[derive(Resource, Default)]
+pub struct NewHighScore(pub Option<(GameMode, usize)>);
+
+impl HighScoreTable {
+    fn load(name: &str) -> HighScoreTable {
+        let Some(content) = storage::read(name) else {
+            return HighScoreTable::default();
+        };
+        ron::from_str(&content).unwrap_or_else(|error| {
+            warn!("Ignoring unreadable high scores {}: {}", name, error);
+            HighScoreTable::default()
+        })
+    }
+
+    fn save(&self, name: &str) {
+        let result = ron::ser::to_string_pretty(self, default())
+            .map_err(|error| error.to_string())
+            .and_then(|content| storage::write(name, &content));
+        if let Err(error) = result {
+            error!("Could not save high scores {}: {}", name, error);
+        }
+    }
+
+    /// Adds the run at its place and returns that place,
+    /// unless it is not good enough for the table. Earlier runs stay ahead of equal scores
+    pub fn insert(&mut self, high_score: HighScore) -> Option<usize> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.score < high_score.score)
+            .unwrap_or(self.entries.len());
+        if position >= HIGH_SCORE_COUNT {
+            return None;
+        }
+        self.entries.insert(position, high_score);
+        self.entries.truncate(HIGH_SCORE_COUNT);
+        Some(position)
+    }
+}
+
+fn forget_new_high_score(mut new_high_score: ResMut<NewHighScore>) {
+    new_high_score.0 = None;
+}
+
+pub fn record_high_score(
+    game_score: Res<GameScore>,
+    game_rng: Res<GameRng>,
+    mode: Res<GameMode>,
+    challenge: Res<DailyChallenge>,
+    mut high_scores: ResMut<HighScores>,
+    mut new_high_score: ResMut<NewHighScore>,
+    balance: Res<Balance>,
+) {
+    new_high_score.0 = None;
+    let (table, storage_name) = match (*mode, challenge.0) {
+        (GameMode::Classic, _) => (&mut high_scores.classic, CLASSIC_STORAGE_NAME),
+        (GameMode::Daily, Some(day)) => {
+            // A new challenge starts with an empty table
+            if high_scores.daily.day != Some(day) {
+                high_scores.daily = HighScoreTable {
+                    day: Some(day),
+                    entries: Vec::new(),
+                };
+            }
+            (&mut high_scores.daily, DAILY_STORAGE_NAME)
+        }
+        // The seed was fixed some other way, the run was not the real challenge
+        (GameMode::Daily, None) => return,
+    };
+    let position = table.insert(HighScore {
+        score: game_score.final_score(&balance.difficulty),
+        distance: game_score.distance_traveled,
+        time: game_score.elapsed_time.elapsed_secs(),
+        preset: game_score.preset,
+        seed: game_rng.seed(),
+        date: storage::now(),
+    });
+    if let Some(position) = position {
+        table.save(storage_name);
+        new_high_score.0 = Some((*mode, position));
+    }
+}
+
+/// Lists the high scores of the mode under the given parent, the last run highlighted.
+/// The daily challenge shows the runs of the given day
+pub fn spawn_high_score_table(
+    parent: &mut ChildBuilder,
+    font_assets: &FontAssets,
+    high_scores: &HighScores,
+    mode: GameMode,
+    day: u64,
+    new_high_score: &NewHighScore,
+) {
+    let title = match mode {
+        GameMode::Classic => "High scores".to_string(),
+        GameMode::Daily => format!("Daily best of {}", format_date(day * 86_400)),
+    };
+    let entries = high_scores.entries(mode, day);
+    let highlight = new_high_score
+        .0
+        .filter(|(new_mode, _)| *new_mode == mode)
+        .map(|(_, position)| position);
+    let row = |text: String, color: Color| {
+        TextBundle::from_section(
+            text,
+            TextStyle {
+                font: font_assets.fira_mono.clone(),
+                font_size: 14.0,
+                color,
+            },
+        )
+    };
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::FlexStart,
+                margin: UiRect::all(Val::Px(10.)),
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            background_color: Color::rgba(0.15, 0.15, 0.15, 0.8).into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    title,
+                    TextStyle {
+                        font: font_assets.fira_sans.clone(),
+                        font_size: 24.0,
+                        color: Color::rgb(0.9, 0.9, 0.9),
+                    },
+                )
+                .with_style(Style {
+                    align_self: AlignSelf::Center,
+                    margin: UiRect::bottom(Val::Px(5.)),
+                    ..default()
+                }),
+            );
+            if entries.is_empty() {
+                parent.spawn(row(
+                    "No runs yet, go and set one!".to_string(),
+                    Color::rgb(0.8, 0.8, 0.8),
+                ));
+                return;
+            }
+            parent.spawn(row(
+                format!(
+                    "{:>2}  {:>6} {:>6} {:>6}  {:<9} {:>20}  {}",
+                    "#", "Score", "Dist", "Time", "Preset", "Seed", "Date"
+                ),
+                Color::rgb(0.6, 0.6, 0.6),
+            ));
+            for (position, entry) in entries.iter().enumerate() {
+                let color = if highlight == Some(position) {
+                    HIGHLIGHT_COLOR
+                } else {
+                    Color::rgb(0.9, 0.9, 0.9)
+                };
+                parent.spawn(row(
+                    format!(
+                        "{:>2}. {:>6} {:>5.0}m {:>5.0}s  {:<9} {:>20}  {}",
+                        position + 1,
+                        entry.score,
+                        entry.distance,
+                        entry.time,
+                        entry.preset.name(),
+                        entry.seed,
+                        format_date(entry.date)
+                    ),
+                    color,
+                ));
+            }
+        });
+}
+
+/// `YYYY-MM-DD` of the given seconds since the unix epoch, in UTC
+pub fn format_date(seconds: u64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (seconds / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn high_score(score: i32) -> HighScore {
+        HighScore {
+            score,
+            distance: 0.,
+            time: 0.,
+            preset: DifficultyPreset::Normal,
+            seed: 0,
+            date: 0,
+        }
+    }
+
+    fn scores(table: &HighScoreTable) -> Vec<i32> {
+        table.entries.iter().map(|entry| entry.score).collect()
+    }
+
+    #[test]
+    fn insert_keeps_the_best_first() {
+        let mut table = HighScoreTable::default();
+        assert_eq!(table.insert(high_score(20)), Some(0));
+        assert_eq!(table.insert(high_score(40)), Some(0));
+        assert_eq!(table.insert(high_score(30)), Some(1));
+        assert_eq!(table.insert(high_score(10)), Some(3));
+        assert_eq!(scores(&table), vec![40, 30, 20, 10]);
+    }
+
+    #[test]
+    fn insert_puts_ties_after_earlier_runs() {
+        let mut table = HighScoreTable::default();
+        table.insert(high_score(20));
+        let mut later = high_score(20);
+        later.seed = 1;
+        assert_eq!(table.insert(later), Some(1));
+        assert_eq!(table.entries[0].seed, 0);
+    }
+
+    #[test]
+    fn insert_truncates_to_the_table_size() {
+        let mut table = HighScoreTable::default();
+        for score in 1..=HIGH_SCORE_COUNT as i32 {
+            table.insert(high_score(score * 10));
+        }
+        assert_eq!(table.insert(high_score(5)), None);
+        assert_eq!(table.entries.len(), HIGH_SCORE_COUNT);
+
+        assert_eq!(table.insert(high_score(15)), Some(HIGH_SCORE_COUNT - 1));
+        assert_eq!(table.entries.len(), HIGH_SCORE_COUNT);
+        assert_eq!(table.entries.last().unwrap().score, 15);
+
+        assert_eq!(table.insert(high_score(1000)), Some(0));
+        assert_eq!(table.entries.len(), HIGH_SCORE_COUNT);
+        assert_eq!(table.entries.last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn format_date_is_utc_calendar_date() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(1_709_251_199), "2024-02-29");
+    }
+}
@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::MainCamera, health::Knockback, player::Movement, simulation_running, GameState,
+    SimulationSet,
+};
+
+pub struct InterpolationPlugin;
+
+/// The simulation moves things in fixed steps, which looks choppy whenever the frame rate
+/// does not match the tick rate. This plugin renders moving entities in between
+/// their last two simulated transforms and puts the simulated transform back before the next tick.
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(track_moving_entities.in_base_set(CoreSet::PostUpdate))
+            .add_system(
+                restore_simulated_transforms
+                    .before(SimulationSet::Actions)
+                    .run_if(simulation_running)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(
+                record_simulated_transforms
+                    .after(SimulationSet::Logic)
+                    .run_if(simulation_running)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(interpolate_transforms.in_set(OnUpdate(GameState::Playing)));
+    }
+}
+
+#[derive(Component)]
+pub struct Interpolated {
+    previous: Transform,
+    current: Transform,
+}
+
+fn track_moving_entities(
+    mut commands: Commands,
+    moving_q: Query<
+        (Entity, &Transform),
+        Or<(Added<Movement>, Added<Knockback>, Added<MainCamera>)>,
+    >,
+) {
+    for (entity, transform) in moving_q.iter() {
+        commands.entity(entity).insert(Interpolated {
+            previous: *transform,
+            current: *transform,
+        });
+    }
+}
+
+fn restore_simulated_transforms(mut interpolated_q: Query<(&mut Transform, &Interpolated)>) {
+    for (mut transform, interpolated) in interpolated_q.iter_mut() {
+        *transform = interpolated.current;
+    }
+}
+
+fn record_simulated_transforms(mut interpolated_q: Query<(&Transform, &mut Interpolated)>) {
+    for (transform, mut interpolated) in interpolated_q.iter_mut() {
+        interpolated.previous = interpolated.current;
+        interpolated.current = *transform;
+    }
+}
+
+fn interpolate_transforms(
+    fixed_time: Res<FixedTime>,
+    mut interpolated_q: Query<(&mut Transform, &Interpolated)>,
+) {
+    // How far we already are into the next tick
+    let alpha =
+        (fixed_time.accumulated().as_secs_f32() / fixed_time.period.as_secs_f32()).clamp(0., 1.);
+    for (mut transform, interpolated) in interpolated_q.iter_mut() {
+        transform.translation = interpolated
+            .previous
+            .translation
+            .lerp(interpolated.current.translation, alpha);
+        transform.rotation = interpolated
+            .previous
+            .rotation
+            .slerp(interpolated.current.rotation, alpha);
+    }
+}
@@ -1,27 +1,61 @@
-mod actions;
-mod audio;
-mod difficulty;
+// Bevy systems naturally take many parameters with long query types
+#![allow(clippy::too_many_arguments, clippy::type_complexity)]
+
+mod achievements;
+pub mod actions;
+pub mod audio;
+pub mod balance;
+pub mod camera;
+pub mod collision;
+mod controls;
+pub mod daily;
+pub mod difficulty;
 mod end;
-mod enemy;
-mod environment;
-mod health;
+pub mod enemy;
+pub mod environment;
+mod ghost;
+mod graphics;
+pub mod health;
+mod high_scores;
+mod interpolation;
+pub mod lifetime;
 mod loading;
 mod menu;
-mod obstacle;
+pub mod obstacle;
 mod pause;
-mod player;
-mod power_up;
-mod score;
+pub mod player;
+pub mod power_up;
+pub mod random;
+pub mod replay;
+pub mod score;
+pub mod spawn;
+pub mod stats;
+mod storage;
 mod ui;
 
-use crate::actions::ActionsPlugin;
-use crate::audio::InternalAudioPlugin;
+use crate::achievements::AchievementsPlugin;
+use crate::actions::{Actions, ActionsPlugin};
+use crate::audio::{InternalAudioPlugin, SoundEvent};
+use crate::balance::{Balance, BalancePlugin};
+use crate::camera::CameraPlugin;
+use crate::collision::CollisionPlugin;
+use crate::controls::ControlsPlugin;
+use crate::daily::DailyPlugin;
 use crate::enemy::EnemyPlugin;
 use crate::environment::EnvironmentPlugin;
+use crate::ghost::GhostPlugin;
+use crate::graphics::GraphicsPlugin;
+use crate::high_scores::HighScoresPlugin;
+use crate::interpolation::InterpolationPlugin;
+use crate::lifetime::LifetimePlugin;
 use crate::loading::LoadingPlugin;
 use crate::menu::MenuPlugin;
 use crate::obstacle::ObstaclePlugin;
 use crate::player::PlayerPlugin;
+use crate::random::RandomPlugin;
+use crate::replay::ReplayPlugin;
+use crate::spawn::SpawnPlugin;
+use crate::stats::StatsPlugin;
 
 use bevy::app::{App, AppExit};
 #[cfg(debug_assertions)]
@@ -39,7 +73,7 @@ use ui::UiPlugin;
 // See https://bevy-cheatbook.github.io/programming/states.html
 // Or https://github.com/bevyengine/bevy/blob/main/examples/ecs/state.rs
 #[derive(States, Default, Clone, Eq, PartialEq, Debug, Hash)]
-enum GameState {
+pub enum GameState {
     // During the loading State the LoadingPlugin will load our assets
     #[default]
     Loading,
@@ -51,44 +85,141 @@ enum GameState {
     Restart,
     // Here the menu is drawn and waiting for player interaction
     Menu,
+    // Everything unlocked so far, reached from the menu
+    Achievements,
+    // Rebinding the keys and mouse buttons, reached from the menu
+    Controls,
     Exit,
 }
 
+/// The full game: simulation plus everything needed to see, hear and control it
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_state::<GameState>()
-            .add_state::<Difficulty>()
+        app.add_plugin(SimulationPlugin)
+            .add_plugin(BalancePlugin)
             .add_plugin(LoadingPlugin)
             .add_plugin(MenuPlugin)
             .add_plugin(ActionsPlugin)
             .add_plugin(InternalAudioPlugin)
-            .add_plugin(PlayerPlugin)
-            .add_plugin(HealthPlugin)
-            .add_plugin(EnemyPlugin)
-            .add_plugin(EnvironmentPlugin)
-            .add_plugin(ObstaclePlugin)
-            .add_plugin(PowerUpPlugin)
+            .add_plugin(GraphicsPlugin)
+            .add_plugin(InterpolationPlugin)
             .add_plugin(UiPlugin)
-            .add_plugin(ScorePlugin)
             .add_plugin(PausePlugin)
             .add_plugin(EndPlugin)
-            .add_plugin(DifficultyPlugin)
+            .add_plugin(HighScoresPlugin)
+            .add_plugin(AchievementsPlugin)
+            .add_plugin(ControlsPlugin)
+            .add_plugin(GhostPlugin)
             .add_system(exit_system.in_schedule(OnEnter(GameState::Exit)))
-            .add_system(play_after_init.in_schedule(OnEnter(GameState::Init)))
-            .add_system(init_after_restart.in_schedule(OnEnter(GameState::Restart)))
             .add_system(change_cursor.in_schedule(OnEnter(GameState::Playing)))
             .add_system(change_cursor_back.in_schedule(OnExit(GameState::Playing)));
 
         #[cfg(debug_assertions)]
         {
-            app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+            app.add_plugin(FrameTimeDiagnosticsPlugin)
                 .add_plugin(LogDiagnosticsPlugin::default());
         }
     }
 }
 
+/// Length of one simulation tick in seconds
+pub const SIMULATION_TIMESTEP: f32 = 1. / 60.;
+
+/// Order of the gameplay systems within one simulation tick.
+/// All of them run in [`CoreSchedule::FixedUpdate`] and only while [`simulation_running`].
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum SimulationSet {
+    /// Decide on the player's actions, either from live input or from a replay
+    Actions,
+    /// Apply the player's actions
+    Input,
+    /// Move everything according to its movement
+    Movement,
+    /// Find out what hit what
+    Collision,
+    /// Take the health the hits cost and find out what died
+    Damage,
+    /// React to what happened: deaths, scoring, spawning and shooting
+    Logic,
+}
+
+/// The gameplay loop without any window, renderer, audio device or input devices.
+///
+/// It only needs `MinimalPlugins` together with the `TransformPlugin` and `HierarchyPlugin`,
+/// so it can run headless and be driven tick by tick:
+/// set `NextState(Some(GameState::Init))` to start a run and steer the player through [`Actions`].
+/// Sounds are only announced as [`SoundEvent`]s and nothing has a sprite attached.
+///
+/// Gameplay runs on a fixed timestep of [`SIMULATION_TIMESTEP`],
+/// so the outcome does not depend on the frame rate.
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.edit_schedule(CoreSchedule::FixedUpdate, |schedule| {
+            schedule.configure_sets(
+                (
+                    SimulationSet::Actions,
+                    SimulationSet::Input,
+                    SimulationSet::Movement,
+                    SimulationSet::Collision,
+                    SimulationSet::Damage,
+                    SimulationSet::Logic,
+                )
+                    .chain(),
+            );
+            for set in [
+                SimulationSet::Actions,
+                SimulationSet::Input,
+                SimulationSet::Movement,
+                SimulationSet::Collision,
+                SimulationSet::Damage,
+                SimulationSet::Logic,
+            ] {
+                schedule.configure_set(set.run_if(simulation_running));
+            }
+        });
+
+        app.add_state::<GameState>()
+            .add_state::<Difficulty>()
+            .insert_resource(FixedTime::new_from_secs(SIMULATION_TIMESTEP))
+            .init_resource::<Actions>()
+            .init_resource::<Balance>()
+            .add_event::<SoundEvent>()
+            .add_plugin(RandomPlugin)
+            .add_plugin(DailyPlugin)
+            .add_plugin(ReplayPlugin)
+            .add_plugin(CameraPlugin)
+            .add_plugin(CollisionPlugin)
+            .add_plugin(SpawnPlugin)
+            .add_plugin(LifetimePlugin)
+            .add_plugin(PlayerPlugin)
+            .add_plugin(HealthPlugin)
+            .add_plugin(EnemyPlugin)
+            .add_plugin(EnvironmentPlugin)
+            .add_plugin(ObstaclePlugin)
+            .add_plugin(PowerUpPlugin)
+            .add_plugin(ScorePlugin)
+            .add_plugin(StatsPlugin)
+            .add_plugin(DifficultyPlugin)
+            .add_system(play_after_init.in_schedule(OnEnter(GameState::Init)))
+            .add_system(init_after_restart.in_schedule(OnEnter(GameState::Restart)));
+    }
+}
+
+/// The simulation only ticks while playing and holds still while a state change is pending.
+/// State changes are applied once per frame, but a frame can contain several ticks;
+/// without waiting the same run could play out differently on different machines.
+pub fn simulation_running(
+    state: Res<State<GameState>>,
+    next_state: Res<NextState<GameState>>,
+    next_difficulty: Res<NextState<Difficulty>>,
+) -> bool {
+    state.0 == GameState::Playing && next_state.0.is_none() && next_difficulty.0.is_none()
+}
+
 fn play_after_init(mut state: ResMut<NextState<GameState>>) {
     state.set(GameState::Playing);
 }
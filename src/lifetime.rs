@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::{camera::MainCamera, SimulationSet};
+
+/// Things this far below the camera have left the screen for good
+pub const OUT_OF_SIGHT: f32 = 600.;
+
+pub struct LifetimePlugin;
+
+/// This plugin removes transient entities (bullets, obstacles, enemies, power ups…)
+/// as soon as they are not part of the game anymore, see [`Lifetime`]
+impl Plugin for LifetimePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(
+            cull_entities
+                .in_set(SimulationSet::Logic)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
+    }
+}
+
+/// Despawns the entity once any of the set limits is reached
+#[derive(Component, Default)]
+pub struct Lifetime {
+    /// How long the entity may exist
+    pub max_age: Option<Duration>,
+    /// How far the entity may fall behind the camera
+    pub max_distance_behind_camera: Option<f32>,
+    /// How far the entity may get from where it was first seen
+    pub max_range: Option<f32>,
+    age: Duration,
+    origin: Option<Vec2>,
+}
+
+impl Lifetime {
+    pub fn behind_camera(distance: f32) -> Self {
+        Lifetime {
+            max_distance_behind_camera: Some(distance),
+            ..default()
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn with_max_range(mut self, max_range: f32) -> Self {
+        self.max_range = Some(max_range);
+        self
+    }
+
+    fn is_over(&self, position: Vec2, camera_y: f32) -> bool {
+        self.max_age.is_some_and(|max_age| self.age >= max_age)
+            || self
+                .max_distance_behind_camera
+                .is_some_and(|distance| position.y < camera_y - distance)
+            || self
+                .max_range
+                .zip(self.origin)
+                .is_some_and(|(range, origin)| position.distance(origin) > range)
+    }
+}
+
+fn cull_entities(
+    mut commands: Commands,
+    fixed_time: Res<FixedTime>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    mut lifetimes_q: Query<(Entity, &Transform, &mut Lifetime)>,
+) {
+    let camera_y = camera_query.get_single().unwrap().translation.y;
+    for (entity, transform, mut lifetime) in lifetimes_q.iter_mut() {
+        let position = transform.translation.truncate();
+        lifetime.age += fixed_time.period;
+        lifetime.origin.get_or_insert(position);
+        if lifetime.is_over(position, camera_y) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::balance::Balance;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
@@ -15,7 +16,8 @@ impl Plugin for LoadingPlugin {
         )
         .add_collection_to_loading_state::<_, FontAssets>(GameState::Loading)
         .add_collection_to_loading_state::<_, AudioAssets>(GameState::Loading)
-        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading);
+        .add_collection_to_loading_state::<_, TextureAssets>(GameState::Loading)
+        .add_collection_to_loading_state::<_, ConfigAssets>(GameState::Loading);
     }
 }
 
@@ -54,6 +56,14 @@ pub struct AudioAssets {
     pub repair: Handle<AudioSource>,
 }
 
+// Keeps the balance loaded, so changes to the file are applied while the game runs
+#[derive(AssetCollection, Resource)]
+pub struct ConfigAssets {
+    #[allow(dead_code)]
+    #[asset(path = "game.balance.ron")]
+    pub balance: Handle<Balance>,
+}
+
 #[derive(AssetCollection, Resource)]
 pub struct TextureAssets {
     #[asset(path = "textures/boat2-36x64.png")]
@@ -78,12 +88,17 @@ pub struct TextureAssets {
     pub enemy_pirate1_dmg2: Handle<Image>,
     #[asset(path = "textures/enemy-pirate1-crashed.png")]
     pub enemy_pirate1_crashed: Handle<Image>,
+    // Second pirate ship skin, not used by any ship yet
+    #[allow(dead_code)]
     #[asset(path = "textures/enemy-pirate2-full.png")]
     pub enemy_pirate2: Handle<Image>,
+    #[allow(dead_code)]
     #[asset(path = "textures/enemy-pirate2-damage1.png")]
     pub enemy_pirate2_dmg1: Handle<Image>,
+    #[allow(dead_code)]
     #[asset(path = "textures/enemy-pirate2-damage2.png")]
     pub enemy_pirate2_dmg2: Handle<Image>,
+    #[allow(dead_code)]
     #[asset(path = "textures/enemy-pirate2-crashed.png")]
     pub enemy_pirate2_crashed: Handle<Image>,
     #[asset(path = "textures/bullet-32x32.png")]
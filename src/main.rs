@@ -5,29 +5,59 @@ use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 use bevy::winit::WinitWindows;
 use bevy::DefaultPlugins;
+use grand_escape::random::FixedSeed;
+use grand_escape::replay::{Playback, Replay, ReplayOutput};
 use grand_escape::GamePlugin;
 use std::io::Cursor;
+use std::path::PathBuf;
 use winit::window::Icon;
 
 fn main() {
+    // `--seed <number>` replays the same river, enemies and drops in every run
+    let seed = arg_value("--seed").and_then(|seed| seed.parse().ok());
+    // `--record <file>` saves the inputs of the last run, `--replay <file>` plays such a file back
+    let record = arg_value("--record").map(PathBuf::from);
+    let playback = arg_value("--replay")
+        .map(|path| {
+            Replay::load(path.as_ref())
+                .unwrap_or_else(|error| panic!("Could not load replay {}: {}", path, error))
+        })
+        .map(Playback::new)
+        .unwrap_or_default();
+
     App::new()
         .insert_resource(Msaa::Off)
         .insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "The grand escape".to_string(),
-                resolution: (760., 800.).into(),
-                canvas: Some("#bevy".to_owned()),
-                resizable: false,
-                ..default()
-            }),
-            ..default()
-        }))
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        title: "The grand escape".to_string(),
+                        resolution: (760., 800.).into(),
+                        canvas: Some("#bevy".to_owned()),
+                        resizable: false,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                // Pick up changes to the game balance without restarting
+                .set(AssetPlugin {
+                    watch_for_changes: true,
+                    ..default()
+                }),
+        )
         .add_plugin(GamePlugin)
+        .insert_resource(FixedSeed(seed))
+        .insert_resource(ReplayOutput(record))
+        .insert_resource(playback)
         .add_system(set_window_icon.on_startup())
         .run();
 }
 
+fn arg_value(name: &str) -> Option<String> {
+    std::env::args().skip_while(|arg| arg != name).nth(1)
+}
+
 // Sets the icon on windows and X11
 fn set_window_icon(
     windows: NonSend<WinitWindows>,
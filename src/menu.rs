@@ -1,6 +1,12 @@
+use crate::actions::{ActiveGamepad, AimMode, GameControl, InputMap};
+use crate::daily::{today, GameMode};
+use crate::difficulty::DifficultyPreset;
+use crate::ghost::GhostSettings;
+use crate::high_scores::{spawn_high_score_table, HighScores, NewHighScore};
 use crate::loading::{FontAssets, TextureAssets};
 use crate::GameState;
 use bevy::prelude::*;
+use bevy::ui::UiSystem;
 
 pub struct MenuPlugin;
 
@@ -9,10 +15,26 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ButtonColors>()
+            .add_system(
+                navigate_with_gamepad
+                    .in_base_set(CoreSet::PreUpdate)
+                    .after(UiSystem::Focus),
+            )
             .add_system(setup_menu.in_schedule(OnEnter(GameState::Menu)))
             .add_system(click_play_button.in_set(OnUpdate(GameState::Menu)))
+            .add_system(select_preset.in_set(OnUpdate(GameState::Menu)))
+            .add_system(select_mode.in_set(OnUpdate(GameState::Menu)))
+            .add_system(toggle_ghost.in_set(OnUpdate(GameState::Menu)))
+            .add_system(
+                refresh_high_scores
+                    .after(select_mode)
+                    .run_if(resource_changed::<GameMode>())
+                    .in_set(OnUpdate(GameState::Menu)),
+            )
             .add_system(click_play_button.in_set(OnUpdate(GameState::Paused)))
             .add_system(click_play_button.in_set(OnUpdate(GameState::End)))
+            .add_system(click_play_button.in_set(OnUpdate(GameState::Achievements)))
+            .add_system(click_play_button.in_set(OnUpdate(GameState::Controls)))
             .add_system(cleanup_menu.in_schedule(OnExit(GameState::Menu)));
     }
 }
@@ -21,6 +43,7 @@ impl Plugin for MenuPlugin {
 pub struct ButtonColors {
     pub normal: Color,
     pub hovered: Color,
+    pub selected: Color,
 }
 
 impl Default for ButtonColors {
@@ -28,31 +51,67 @@ impl Default for ButtonColors {
         ButtonColors {
             normal: Color::rgb(0.15, 0.15, 0.15),
             hovered: Color::rgb(0.25, 0.25, 0.25),
+            selected: Color::rgb(0.45, 0.3, 0.1),
         }
     }
 }
 
-#[derive(Component)]
-pub struct MainCamera;
-
 #[derive(Component)]
 pub struct MenuBundle;
 
 #[derive(Component)]
+#[allow(clippy::enum_variant_names)]
 pub enum ButtonAction {
     PlayButton,
     ExitButton,
     ContinueButton,
     RestartButton,
+    AchievementsButton,
+    ControlsButton,
+    BackButton,
 }
 
+/// Picks the difficulty preset for the next runs
+#[derive(Component)]
+pub struct PresetButton(DifficultyPreset);
+
+/// Picks between classic runs and the daily challenge for the next runs
+#[derive(Component)]
+pub struct ModeButton(GameMode);
+
+/// Turns racing against the ghost of the best run on and off
+#[derive(Component)]
+pub struct GhostButton;
+
+/// Holds the high scores of the selected mode
+#[derive(Component)]
+struct MenuHighScores;
+
 fn setup_menu(
     mut commands: Commands,
     font_assets: Res<FontAssets>,
     button_colors: Res<ButtonColors>,
     textures: Res<TextureAssets>,
+    high_scores: Res<HighScores>,
+    new_high_score: Res<NewHighScore>,
+    mode: Res<GameMode>,
+    input_map: Res<InputMap>,
 ) {
-    commands.spawn(Camera2dBundle::default()).insert(MainCamera);
+    // The instructions name whatever the controls are bound to right now
+    let control = |control| input_map.describe(control);
+    let aiming = match input_map.aim_mode {
+        AimMode::Mouse => "Set yer sights on enemies with yer trusty mouse, move it to aim.".to_string(),
+        AimMode::Keys => format!(
+            "Turn yer cannon with {} and {} to set yer sights on enemies.",
+            control(GameControl::AimLeft),
+            control(GameControl::AimRight),
+        ),
+        AimMode::LockOn => format!(
+            "Yer cannon sets its sights on the nearest enemy by itself, turn it with {} and {} when there be none.",
+            control(GameControl::AimLeft),
+            control(GameControl::AimRight),
+        ),
+    };
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -138,7 +197,7 @@ fn setup_menu(
                             ..default()
                         }));
                     parent.spawn(TextBundle::from_section(
-                        "Use the arrow keys or A and D keys to steer yer boat. Change direction to navigate and avoid obstacles on the high seas.",
+                        format!("Use {} and {} to steer yer boat, {} and {} to speed up or slow down. Change direction to navigate and avoid obstacles on the high seas.", control(GameControl::Left), control(GameControl::Right), control(GameControl::Up), control(GameControl::Down)),
                         TextStyle {
                             font: font_assets.fira_sans_reg.clone(),
                             font_size: 20.0,
@@ -150,7 +209,7 @@ fn setup_menu(
                             ..default()
                         }));
                     parent.spawn(TextBundle::from_section(
-                        "Set yer sights on enemies with yer trusty mouse. Move it to aim, and fire with the mouse button to blast 'em!",
+                        format!("{} Fire with {} to blast 'em and {} for a spread of shot!", aiming, control(GameControl::Fire), control(GameControl::AltFire)),
                         TextStyle {
                             font: font_assets.fira_sans_reg.clone(),
                             font_size: 20.0,
@@ -162,7 +221,7 @@ fn setup_menu(
                             ..default()
                         }));
                     parent.spawn(TextBundle::from_section(
-                        "Hit yer Escape button if when ye be needin' a break to pause the game.",
+                        format!("Hit {} if when ye be needin' a break to pause the game.", control(GameControl::Pause)),
                         TextStyle {
                             font: font_assets.fira_sans_reg.clone(),
                             font_size: 20.0,
@@ -186,6 +245,100 @@ fn setup_menu(
                     ..default()
                 })
                 .with_children(|parent| {
+                    parent
+                        .spawn(NodeBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Auto),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for mode in GameMode::ALL {
+                                parent
+                                    .spawn(ButtonBundle {
+                                        style: Style {
+                                            margin: UiRect::horizontal(Val::Px(5.)),
+                                            padding: UiRect::all(Val::Px(10.)),
+                                            ..default()
+                                        },
+                                        background_color: button_colors.normal.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            mode.name(),
+                                            TextStyle {
+                                                font: font_assets.fira_sans_reg.clone(),
+                                                font_size: 24.0,
+                                                color: Color::rgb(0.9, 0.9, 0.9),
+                                            },
+                                        ));
+                                    })
+                                    .insert(ModeButton(mode));
+                            }
+                            // Keeps the modes apart from the presets
+                            parent.spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::width(Val::Px(20.)),
+                                    ..default()
+                                },
+                                ..default()
+                            });
+                            for preset in DifficultyPreset::ALL {
+                                parent
+                                    .spawn(ButtonBundle {
+                                        style: Style {
+                                            margin: UiRect::horizontal(Val::Px(5.)),
+                                            padding: UiRect::all(Val::Px(10.)),
+                                            ..default()
+                                        },
+                                        background_color: button_colors.normal.into(),
+                                        ..default()
+                                    })
+                                    .with_children(|parent| {
+                                        parent.spawn(TextBundle::from_section(
+                                            preset.name(),
+                                            TextStyle {
+                                                font: font_assets.fira_sans_reg.clone(),
+                                                font_size: 24.0,
+                                                color: Color::rgb(0.9, 0.9, 0.9),
+                                            },
+                                        ));
+                                    })
+                                    .insert(PresetButton(preset));
+                            }
+                            // Keeps the presets apart from the ghost
+                            parent.spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::width(Val::Px(20.)),
+                                    ..default()
+                                },
+                                ..default()
+                            });
+                            parent
+                                .spawn(ButtonBundle {
+                                    style: Style {
+                                        margin: UiRect::horizontal(Val::Px(5.)),
+                                        padding: UiRect::all(Val::Px(10.)),
+                                        ..default()
+                                    },
+                                    background_color: button_colors.normal.into(),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        "Ghost",
+                                        TextStyle {
+                                            font: font_assets.fira_sans_reg.clone(),
+                                            font_size: 24.0,
+                                            color: Color::rgb(0.9, 0.9, 0.9),
+                                        },
+                                    ));
+                                })
+                                .insert(GhostButton);
+                        });
                     parent
                         .spawn(ButtonBundle {
                             style: Style {
@@ -210,6 +363,48 @@ fn setup_menu(
                                 ..default()
                             });
                         }).insert(ButtonAction::PlayButton);
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Auto),
+                                padding: UiRect::all(Val::Px(10.)),
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Achievements",
+                                TextStyle {
+                                    font: font_assets.fira_sans_reg.clone(),
+                                    font_size: 24.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ));
+                        })
+                        .insert(ButtonAction::AchievementsButton);
+                    parent
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Auto),
+                                padding: UiRect::all(Val::Px(10.)),
+                                ..default()
+                            },
+                            background_color: button_colors.normal.into(),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                "Controls",
+                                TextStyle {
+                                    font: font_assets.fira_sans_reg.clone(),
+                                    font_size: 24.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ));
+                        })
+                        .insert(ButtonAction::ControlsButton);
                     parent
                         .spawn(ButtonBundle {
                             style: Style {
@@ -234,9 +429,115 @@ fn setup_menu(
                             });
                         }).insert(ButtonAction::ExitButton);
                 });
+            parent
+                .spawn(NodeBundle::default())
+                .insert(MenuHighScores)
+                .with_children(|parent| {
+                    spawn_high_score_table(
+                        parent,
+                        &font_assets,
+                        &high_scores,
+                        *mode,
+                        today(),
+                        &new_high_score,
+                    );
+                });
         });
 }
 
+/// Moves the focus between the buttons of whatever screen is shown with the d-pad or left stick
+/// and presses the focused one with the south button.
+/// The focused button looks hovered; moving the mouse hands control back to it
+fn navigate_with_gamepad(
+    gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut cursor_moved_events: EventReader<CursorMoved>,
+    mut focused: Local<Option<Entity>>,
+    mut stick_pushed: Local<bool>,
+    mut buttons_q: Query<
+        (
+            Entity,
+            &GlobalTransform,
+            &ComputedVisibility,
+            &mut Interaction,
+        ),
+        With<Button>,
+    >,
+) {
+    if cursor_moved_events.iter().last().is_some() {
+        *focused = None;
+    }
+
+    // The stick moves the focus once each time it is pushed, UI coordinates grow downwards
+    let stick = gamepad.left_stick(&gamepad_axes);
+    let stick_direction = if stick.length() < 0.5 {
+        None
+    } else if stick.x.abs() > stick.y.abs() {
+        Some(Vec2::new(stick.x.signum(), 0.))
+    } else {
+        Some(Vec2::new(0., -stick.y.signum()))
+    };
+    let stick_moved = if *stick_pushed { None } else { stick_direction };
+    *stick_pushed = stick_direction.is_some();
+    let direction = [
+        (GamepadButtonType::DPadUp, Vec2::NEG_Y),
+        (GamepadButtonType::DPadDown, Vec2::Y),
+        (GamepadButtonType::DPadLeft, Vec2::NEG_X),
+        (GamepadButtonType::DPadRight, Vec2::X),
+    ]
+    .into_iter()
+    .find(|(button_type, _)| gamepad.just_pressed(&gamepad_buttons, *button_type))
+    .map(|(_, direction)| direction)
+    .or(stick_moved);
+
+    let visible_buttons = buttons_q
+        .iter()
+        .filter(|(_, _, visibility, _)| visibility.is_visible())
+        .map(|(entity, transform, _, _)| (entity, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+    let focused_position = visible_buttons
+        .iter()
+        .find(|(entity, _)| Some(*entity) == *focused)
+        .map(|(_, position)| *position);
+    if focused_position.is_none() {
+        *focused = None;
+    }
+    if let Some(direction) = direction {
+        *focused = match focused_position {
+            // The closest button in that direction, preferring the ones in line with the focused one
+            Some(from) => visible_buttons
+                .iter()
+                .filter_map(|(entity, position)| {
+                    let offset = *position - from;
+                    let along = offset.dot(direction);
+                    (along > 1.).then(|| (*entity, along + 2. * offset.perp_dot(direction).abs()))
+                })
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(entity, _)| entity)
+                .or(*focused),
+            // The first button from the top left
+            None => visible_buttons
+                .iter()
+                .min_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)))
+                .map(|(entity, _)| *entity),
+        };
+    }
+
+    let Some(entity) = *focused else {
+        return;
+    };
+    if let Ok((_, _, _, mut interaction)) = buttons_q.get_mut(entity) {
+        interaction.set_if_neq(
+            if gamepad.just_pressed(&gamepad_buttons, GamepadButtonType::South) {
+                Interaction::Clicked
+            } else {
+                Interaction::Hovered
+            },
+        );
+    }
+}
+
 fn click_play_button(
     button_colors: Res<ButtonColors>,
     mut state: ResMut<NextState<GameState>>,
@@ -260,6 +561,15 @@ fn click_play_button(
                 ButtonAction::RestartButton => {
                     state.set(GameState::Restart);
                 }
+                ButtonAction::AchievementsButton => {
+                    state.set(GameState::Achievements);
+                }
+                ButtonAction::ControlsButton => {
+                    state.set(GameState::Controls);
+                }
+                ButtonAction::BackButton => {
+                    state.set(GameState::Menu);
+                }
             },
             Interaction::Hovered => {
                 *color = button_colors.hovered.into();
@@ -271,6 +581,98 @@ fn click_play_button(
     }
 }
 
+fn select_preset(
+    button_colors: Res<ButtonColors>,
+    mut preset: ResMut<DifficultyPreset>,
+    mut preset_buttons_q: Query<(&Interaction, &PresetButton, &mut BackgroundColor)>,
+) {
+    for (interaction, button, _) in preset_buttons_q.iter() {
+        if *interaction == Interaction::Clicked && *preset != button.0 {
+            *preset = button.0;
+        }
+    }
+    for (interaction, button, mut color) in preset_buttons_q.iter_mut() {
+        *color = if button.0 == *preset {
+            button_colors.selected
+        } else if *interaction == Interaction::Hovered {
+            button_colors.hovered
+        } else {
+            button_colors.normal
+        }
+        .into();
+    }
+}
+
+fn select_mode(
+    button_colors: Res<ButtonColors>,
+    mut mode: ResMut<GameMode>,
+    mut mode_buttons_q: Query<(&Interaction, &ModeButton, &mut BackgroundColor)>,
+) {
+    for (interaction, button, _) in mode_buttons_q.iter() {
+        if *interaction == Interaction::Clicked && *mode != button.0 {
+            *mode = button.0;
+        }
+    }
+    for (interaction, button, mut color) in mode_buttons_q.iter_mut() {
+        *color = if button.0 == *mode {
+            button_colors.selected
+        } else if *interaction == Interaction::Hovered {
+            button_colors.hovered
+        } else {
+            button_colors.normal
+        }
+        .into();
+    }
+}
+
+fn toggle_ghost(
+    button_colors: Res<ButtonColors>,
+    mut ghost_settings: ResMut<GhostSettings>,
+    clicked_q: Query<&Interaction, (Changed<Interaction>, With<GhostButton>)>,
+    mut ghost_button_q: Query<(&Interaction, &mut BackgroundColor), With<GhostButton>>,
+) {
+    for interaction in clicked_q.iter() {
+        if *interaction == Interaction::Clicked {
+            ghost_settings.enabled = !ghost_settings.enabled;
+        }
+    }
+    for (interaction, mut color) in ghost_button_q.iter_mut() {
+        *color = if ghost_settings.enabled {
+            button_colors.selected
+        } else if *interaction == Interaction::Hovered {
+            button_colors.hovered
+        } else {
+            button_colors.normal
+        }
+        .into();
+    }
+}
+
+fn refresh_high_scores(
+    mut commands: Commands,
+    high_scores_q: Query<Entity, With<MenuHighScores>>,
+    font_assets: Res<FontAssets>,
+    high_scores: Res<HighScores>,
+    new_high_score: Res<NewHighScore>,
+    mode: Res<GameMode>,
+) {
+    let Ok(entity) = high_scores_q.get_single() else {
+        return;
+    };
+    let mut high_scores_entity = commands.entity(entity);
+    high_scores_entity.despawn_descendants();
+    high_scores_entity.with_children(|parent| {
+        spawn_high_score_table(
+            parent,
+            &font_assets,
+            &high_scores,
+            *mode,
+            today(),
+            &new_high_score,
+        );
+    });
+}
+
 fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<MenuBundle>>) {
     commands.entity(menu.single()).despawn_recursive();
 }
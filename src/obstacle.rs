@@ -1,167 +1,100 @@
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 use rand::Rng;
-use std::{f32::consts::PI, time::Duration};
+use std::f32::consts::PI;
 
 use crate::{
-    difficulty::Difficulty,
+    audio::SoundEvent,
+    balance::Balance,
     environment::{Collidable, MAP_WIDTH},
-    health::{Health, Mass},
-    loading::{AudioAssets, TextureAssets},
-    menu::MainCamera,
+    health::{DeathEvent, Health},
+    lifetime::{Lifetime, OUT_OF_SIGHT},
     power_up::PowerUp,
-    GameState,
+    random::{GameRng, RngStream},
+    spawn::{direct_spawns, SpawnEvent, SpawnKind, Threat},
+    GameState, SimulationSet,
 };
 
 pub struct ObstaclePlugin;
 
 impl Plugin for ObstaclePlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<ObstacleSpawnTimers>()
-            .add_system(despawn_obstacles.in_schedule(OnEnter(GameState::Restart)))
-            .add_system(detect_dead_obstacles.in_set(OnUpdate(GameState::Playing)))
-            .add_system(spawn_obstacles.in_set(OnUpdate(GameState::Playing)))
-            .add_system(increase_difficulty_medium.in_schedule(OnEnter(Difficulty::Medium)))
-            .add_system(increase_difficulty_hard.in_schedule(OnEnter(Difficulty::Hard)));
+        app.add_system(despawn_obstacles.in_schedule(OnEnter(GameState::Restart)))
+            .add_systems(
+                (detect_dead_obstacles, spawn_obstacles.after(direct_spawns))
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
     }
 }
 
 #[derive(Component)]
-pub struct ObstacleTile;
-
-#[derive(Resource)]
-pub struct ObstacleSpawnTimers {
-    timers: Vec<Timer>,
-}
-
-const OBSTACLE_SIZES: [bevy::prelude::Vec2; 6] = [
-    Vec2::new(55., 57.),
-    Vec2::new(48., 50.),
-    Vec2::new(55., 37.),
-    Vec2::new(32., 11.),
-    Vec2::new(32., 9.),
-    Vec2::new(32., 9.),
-];
-
-impl Default for ObstacleSpawnTimers {
-    fn default() -> Self {
-        ObstacleSpawnTimers {
-            timers: vec![
-                Timer::new(Duration::from_secs(2), TimerMode::Repeating),
-                Timer::new(Duration::from_secs(4), TimerMode::Repeating),
-            ],
-        }
-    }
+pub struct ObstacleTile {
+    /// Index into the obstacle variants of the [`Balance`], rocks first then wood
+    pub variant: usize,
 }
 
 fn spawn_obstacles(
     mut commands: Commands,
-    time: Res<Time>,
-    mut spawn_timers: ResMut<ObstacleSpawnTimers>,
-    textures: Res<TextureAssets>,
-    camera_query: Query<&Transform, With<MainCamera>>,
+    mut spawn_events: EventReader<SpawnEvent>,
+    mut game_rng: ResMut<GameRng>,
+    balance: Res<Balance>,
 ) {
-    let camera_position = camera_query.get_single().unwrap().translation.y;
-    let next_spawn_position = camera_position + 600.;
-    for timer in &mut spawn_timers.timers {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let available_obstacles = [
-                textures.obstacle_rock1.clone(),
-                textures.obstacle_rock2.clone(),
-                textures.obstacle_rock3.clone(),
-                textures.obstacle_wood1.clone(),
-                textures.obstacle_wood2.clone(),
-                textures.obstacle_wood3.clone(),
-            ];
-            let mut rng = rand::thread_rng();
-            let which_one_index = rng.gen_range(0..6);
-            let random_angle = rng.gen_range(0.0..2. * PI);
-            let obstacle_texture = &available_obstacles[which_one_index];
-            let size = OBSTACLE_SIZES[which_one_index];
-            let health = if which_one_index > 2 { 1 } else { 100 };
-            let damage = if which_one_index > 2 { 1 } else { 2 };
-            let mass = if which_one_index > 2 {
-                Mass::Wood
-            } else {
-                Mass::Rock
-            };
-            let immune = which_one_index > 2;
-            let position = get_random_obstacle_spawn_position();
-            commands
-                .spawn(SpriteBundle {
-                    texture: obstacle_texture.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        position,
-                        next_spawn_position,
-                        2.,
-                    ))
+    let balance = &balance.obstacles;
+    let rng = game_rng.stream(RngStream::Obstacles);
+    for event in spawn_events
+        .iter()
+        .filter(|event| event.kind == SpawnKind::Obstacle)
+    {
+        let which_one_index = rng.gen_range(0..balance.variants.len());
+        let random_angle = rng.gen_range(0.0..2. * PI);
+        let variant = &balance.variants[which_one_index];
+        let position = get_random_obstacle_spawn_position(rng);
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(position, event.y, 2.))
                     .with_rotation(Quat::from_rotation_z(random_angle)),
-                    // transform: Transform::from_translation(Vec3::new(0.0, 0.0, 1.)),
-                    ..Default::default()
-                })
-                .insert(ObstacleTile)
-                .insert(Health {
-                    max_health: health,
-                    health_amount: health,
-                    size,
-                    immune_to_bullets: immune,
-                    mass,
-                })
-                .insert(Collidable {
-                    size,
-                    damage,
-                    is_alive: true,
-                });
-            let mut rng = rand::thread_rng();
-            let duration = rng.gen_range(3500..6000);
-            timer.set_duration(Duration::from_millis(duration));
-        }
+                // transform: Transform::from_translation(Vec3::new(0.0, 0.0, 1.)),
+                ..Default::default()
+            })
+            .insert(ObstacleTile {
+                variant: which_one_index,
+            })
+            .insert(Lifetime::behind_camera(OUT_OF_SIGHT))
+            .insert(Threat(event.threat))
+            .insert(Health {
+                max_health: variant.health,
+                health_amount: variant.health,
+                shape: variant.shape,
+                immune_to_bullets: variant.immune_to_bullets,
+                mass: variant.mass,
+            })
+            .insert(Collidable {
+                shape: variant.shape,
+                damage: variant.damage,
+                is_alive: true,
+            });
     }
 }
 
-pub fn get_random_obstacle_spawn_position() -> f32 {
-    let mut rng = rand::thread_rng();
+pub fn get_random_obstacle_spawn_position(rng: &mut impl Rng) -> f32 {
     rng.gen_range(30.0..MAP_WIDTH - 30.)
 }
 
 fn detect_dead_obstacles(
-    mut obstacles_q: Query<(&mut Collidable, &mut Handle<Image>, &Health), Without<PowerUp>>,
-    textures: Res<TextureAssets>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut death_events: EventReader<DeathEvent>,
+    mut obstacles_q: Query<&mut Collidable, Without<PowerUp>>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
-    for (mut obstacle, mut handle, health) in obstacles_q.iter_mut() {
-        if obstacle.is_alive == false {
-            continue;
-        }
-        if health.health_amount <= 0 {
+    for death in death_events.iter() {
+        if let Ok(mut obstacle) = obstacles_q.get_mut(death.target) {
             obstacle.is_alive = false;
-            *handle = textures.obstacle_wood_dead.clone();
-            audio.play(audio_assets.wood_break.clone()).with_volume(0.3);
+            sound_events.send(SoundEvent::ObstacleBroken);
         }
     }
 }
 
-fn despawn_obstacles(
-    mut commands: Commands,
-    obstacle_q: Query<Entity, With<ObstacleTile>>,
-    mut obstacle_spawn_timers: ResMut<ObstacleSpawnTimers>,
-) {
+fn despawn_obstacles(mut commands: Commands, obstacle_q: Query<Entity, With<ObstacleTile>>) {
     for entity in obstacle_q.iter() {
         commands.entity(entity).despawn_recursive();
     }
-    *obstacle_spawn_timers = ObstacleSpawnTimers::default();
-}
-
-fn increase_difficulty_medium(mut spawn_timers: ResMut<ObstacleSpawnTimers>) {
-    spawn_timers
-        .timers
-        .push(Timer::new(Duration::from_secs(5), TimerMode::Repeating));
-}
-
-fn increase_difficulty_hard(mut spawn_timers: ResMut<ObstacleSpawnTimers>) {
-    spawn_timers
-        .timers
-        .push(Timer::new(Duration::from_secs(5), TimerMode::Repeating));
 }
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 
 use crate::{
+    actions::{
+        ActiveGamepad, ActiveGamepadLost, GameControl, InputMap, TouchButton, TouchControls,
+    },
     loading::FontAssets,
     menu::{ButtonAction, ButtonColors},
     GameState,
@@ -20,14 +23,38 @@ impl Plugin for PausePlugin {
 #[derive(Component)]
 pub struct PauseMenuBundle;
 
-fn pause_game(mut state: ResMut<NextState<GameState>>, keyboard_input: Res<Input<KeyCode>>) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+fn pause_game(
+    mut state: ResMut<NextState<GameState>>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    touch_controls: Res<TouchControls>,
+    mut lost_events: EventReader<ActiveGamepadLost>,
+) {
+    // Unplugging the gamepad mid run pauses, so the boat does not crash while it is plugged back in
+    let disconnected = lost_events.iter().count() > 0;
+    if input_map.just_pressed(GameControl::Pause, &keyboard_input, &mouse_input)
+        || gamepad.just_pressed(&gamepad_buttons, GamepadButtonType::Start)
+        || touch_controls.just_pressed(TouchButton::Pause)
+        || disconnected
+    {
         state.set(GameState::Paused);
     }
 }
 
-fn unpause_game(mut state: ResMut<NextState<GameState>>, keyboard_input: Res<Input<KeyCode>>) {
-    if keyboard_input.just_pressed(KeyCode::Escape) {
+fn unpause_game(
+    mut state: ResMut<NextState<GameState>>,
+    input_map: Res<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad: Res<ActiveGamepad>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
+    if input_map.just_pressed(GameControl::Pause, &keyboard_input, &mouse_input)
+        || gamepad.just_pressed(&gamepad_buttons, GamepadButtonType::Start)
+    {
         state.set(GameState::Playing);
     }
 }
@@ -1,16 +1,22 @@
-use std::f32::consts::{FRAC_PI_2, PI};
+use std::f32::consts::PI;
 use std::time::Duration;
 
 use crate::actions::Actions;
+use crate::audio::SoundEvent;
+use crate::balance::Balance;
+use crate::camera::MainCamera;
+use crate::collision::{Shape, SpatialGrid};
 use crate::environment::{Collidable, MAP_HEIGHT, MAP_WIDTH};
-use crate::health::{Bullet, Health, Mass};
-use crate::loading::{AudioAssets, TextureAssets};
-use crate::menu::MainCamera;
-use crate::GameState;
+use crate::health::{
+    apply_knockback, detect_bullet_collisions, Bullet, DamageEvent, DamageKind, DeathEvent, Health,
+    Invulnerability, Knockback, Mass, BULLET_MAX_AGE, BULLET_RANGE,
+};
+use crate::lifetime::{Lifetime, OUT_OF_SIGHT};
+use crate::random::seed_run;
+use crate::score::GameScore;
+use crate::stats::RunStats;
+use crate::{GameState, SimulationSet};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
-use bevy::window::PrimaryWindow;
-use bevy_kira_audio::{Audio, AudioControl};
 
 pub const PLAYER_HEIGHT: f32 = 64.;
 pub const PLAYER_WIDTH: f32 = 28.;
@@ -22,21 +28,43 @@ pub struct PlayerPlugin;
 /// Player logic is only active during the State `GameState::Playing`
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(spawn_player.in_schedule(OnEnter(GameState::Init)))
-            .add_system(despawn_player.in_schedule(OnEnter(GameState::Restart)))
-            .add_system(move_player.in_set(OnUpdate(GameState::Playing)))
-            .add_system(detect_collisions.in_set(OnUpdate(GameState::Playing)))
-            .add_system(
-                camera_follow_player
-                    .in_set(OnUpdate(GameState::Playing))
-                    .after(move_player),
+        app.add_system(
+            spawn_player
+                .after(seed_run)
+                .in_schedule(OnEnter(GameState::Init)),
+        )
+        .add_system(despawn_player.in_schedule(OnEnter(GameState::Restart)))
+        .add_systems(
+            (
+                move_player,
+                move_player_cannon,
+                player_shoot.after(move_player_cannon),
             )
-            .add_system(continuous_movement.in_set(OnUpdate(GameState::Playing)))
-            .add_system(move_player_cannon.in_set(OnUpdate(GameState::Playing)))
-            .add_system(player_shoot.in_set(OnUpdate(GameState::Playing)))
-            .add_system(detect_player_dead.in_set(OnUpdate(GameState::Playing)))
-            .add_system(display_boat_damage.in_set(OnUpdate(GameState::Playing)))
-            .add_system(rotate_transform_to_movement.in_set(OnUpdate(GameState::Playing)));
+                .in_set(SimulationSet::Input)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
+        .add_systems(
+            (
+                // Both add to the translation, adding in a fixed order gives the same position every time
+                continuous_movement.before(apply_knockback),
+                rotate_transform_to_movement,
+                camera_follow_player.after(apply_knockback),
+            )
+                .in_set(SimulationSet::Movement)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
+        .add_system(
+            // Whichever damage event sinks something first is credited with it, so they are sent in a fixed order
+            detect_collisions
+                .after(detect_bullet_collisions)
+                .in_set(SimulationSet::Collision)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        )
+        .add_system(
+            detect_player_dead
+                .in_set(SimulationSet::Logic)
+                .in_schedule(CoreSchedule::FixedUpdate),
+        );
     }
 }
 
@@ -47,7 +75,9 @@ pub struct Player;
 pub struct PlayerCannon {
     pub vector: Vec2,
     pub timer: Timer,
-    pub turn_rate: f32, // how many degrees we allow to turn cannon in one second
+    pub turn_rate: f32, // how many radians we allow to turn cannon in one second
+    /// How many more times the cannon has to reload before it is loaded again, a spread shot takes several
+    pub reloads_left: u32,
 }
 
 // TODO move this into own plugin
@@ -57,50 +87,51 @@ pub struct Movement {
     pub vector: Vec2,
 }
 
-impl Default for Movement {
-    fn default() -> Self {
-        Movement {
-            speed: 120.0,
-            vector: Vec2::new(0., 1.),
-        }
-    }
-}
-
-fn spawn_player(mut commands: Commands, textures: Res<TextureAssets>) {
+fn spawn_player(mut commands: Commands, game_score: Res<GameScore>, balance: Res<Balance>) {
+    let health_multiplier = balance.presets.get(game_score.preset).health_multiplier;
+    let balance = &balance.player;
+    let health = (balance.health as f32 * health_multiplier).round() as i32;
     // Spawn player to the center of the map
     let center_x = MAP_WIDTH / 2.;
     let center_y = MAP_HEIGHT / 2.;
 
     commands
-        .spawn(SpriteBundle {
-            texture: textures.boat.clone(),
+        .spawn(SpatialBundle {
             transform: Transform::from_translation(Vec3::new(center_x, center_y, 5.))
                 .with_rotation(Quat::from_rotation_z(0.)),
             ..Default::default()
         })
         .insert(Player)
         .insert(Health {
-            max_health: 10,
-            health_amount: 10,
-            size: Vec2::new(28., 64.),
+            max_health: health,
+            health_amount: health,
+            shape: Shape::Rectangle { size: PLAYER_SIZE },
             immune_to_bullets: false,
             mass: Mass::Wood,
         })
+        .insert(Invulnerability::new(Duration::from_millis(
+            balance.invulnerability_ms,
+        )))
+        .insert(Knockback::new(balance.knockback_speed))
         .insert(Movement {
-            ..Default::default()
+            speed: balance.speed,
+            vector: Vec2::new(0., 1.),
         })
         .with_children(|parent| {
             parent
-                .spawn(SpriteBundle {
-                    texture: textures.boat_cannon.clone(),
+                .spawn(SpatialBundle {
                     transform: Transform::from_translation(Vec3::new(0., 20., 5.1))
                         .with_rotation(Quat::from_rotation_z(0.)),
                     ..Default::default()
                 })
                 .insert(PlayerCannon {
                     vector: Vec2::new(0., 0.),
-                    timer: Timer::new(Duration::from_millis(1000), TimerMode::Once),
-                    turn_rate: 3. * FRAC_PI_2,
+                    timer: Timer::new(
+                        Duration::from_millis(balance.cannon_reload_ms),
+                        TimerMode::Once,
+                    ),
+                    turn_rate: balance.cannon_turn_rate.to_radians(),
+                    reloads_left: 0,
                 });
         });
 }
@@ -111,13 +142,14 @@ fn despawn_player(mut commands: Commands, player_q: Query<Entity, With<Player>>)
 }
 
 fn continuous_movement(
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     mut movement_query: Query<(&mut Transform, &Movement), With<Movement>>,
 ) {
+    let delta_seconds = fixed_time.period.as_secs_f32();
     for (mut transform, movement) in movement_query.iter_mut() {
         transform.translation += Vec3::new(
-            movement.vector.x * movement.speed * time.delta_seconds(),
-            movement.vector.y * movement.speed * time.delta_seconds(),
+            movement.vector.x * movement.speed * delta_seconds,
+            movement.vector.y * movement.speed * delta_seconds,
             0.,
         );
     }
@@ -132,21 +164,26 @@ fn rotate_transform_to_movement(mut transform_query: Query<(&mut Transform, &Mov
 }
 
 fn move_player(
-    time: Res<Time>,
+    fixed_time: Res<FixedTime>,
     actions: Res<Actions>,
+    balance: Res<Balance>,
     mut player_query: Query<&mut Movement, With<Player>>,
 ) {
     if actions.player_movement.is_none() {
         return;
     }
-    let turn_rate = 1.8;
-    let movement = Vec2::new(
-        actions.player_movement.unwrap().x * turn_rate * time.delta_seconds(),
-        actions.player_movement.unwrap().y * turn_rate * time.delta_seconds(),
+    let turn_rate = balance.player.steering;
+    let delta_seconds = fixed_time.period.as_secs_f32();
+    let steering = Vec2::new(
+        actions.player_movement.unwrap().x * turn_rate * delta_seconds,
+        0.,
     );
+    let acceleration = actions.player_movement.unwrap().y * balance.player.acceleration;
     for mut player_movement in &mut player_query {
-        player_movement.vector += movement;
-        player_movement.vector = player_movement.vector.normalize()
+        player_movement.vector += steering;
+        player_movement.vector = player_movement.vector.normalize();
+        player_movement.speed = (player_movement.speed + acceleration * delta_seconds)
+            .clamp(balance.player.min_speed, balance.player.max_speed);
     }
 }
 
@@ -164,90 +201,84 @@ fn camera_follow_player(
 }
 
 fn detect_collisions(
-    mut player_q: Query<(&Transform, &mut Health), With<Player>>,
-    mut collidables_query: Query<(&Transform, &Collidable, Option<&mut Health>), Without<Player>>,
+    player_q: Query<(Entity, &Transform, &Health), With<Player>>,
+    collidables_query: Query<(&Transform, &Collidable, Option<&Health>), Without<Player>>,
+    grid: Res<SpatialGrid>,
+    mut damage_events: EventWriter<DamageEvent>,
 ) {
-    let (player_transform, mut player_health) = player_q.get_single_mut().unwrap();
-    for (collidable_transform, collidable, collidable_health) in collidables_query.iter_mut() {
-        if collidable.is_alive == false {
+    let (player, player_transform, player_health) = player_q.get_single().unwrap();
+    let player_shape = player_health.shape;
+    let nearby = grid.query(
+        player_transform.translation.truncate(),
+        player_shape.bounds(player_transform),
+    );
+    for entity in nearby {
+        let Ok((collidable_transform, collidable, collidable_health)) =
+            collidables_query.get(entity)
+        else {
+            continue;
+        };
+        if !collidable.is_alive {
             continue;
         }
-        let collision = collide(
-            player_transform.translation,
-            PLAYER_SIZE,
-            collidable_transform.translation,
-            collidable.size,
-        );
-        if Option::is_some(&collision) {
-            player_health.health_amount -= collidable.damage;
-            if let Some(mut col_health) = collidable_health {
-                col_health.health_amount -= 1;
+        if player_shape.intersects(player_transform, &collidable.shape, collidable_transform) {
+            damage_events.send(DamageEvent {
+                source: entity,
+                target: player,
+                amount: collidable.damage,
+                kind: DamageKind::Collision,
+            });
+            if collidable_health.is_some() {
+                damage_events.send(DamageEvent {
+                    source: player,
+                    target: entity,
+                    amount: 1,
+                    kind: DamageKind::Collision,
+                });
             }
         }
     }
 }
 
-fn display_boat_damage(
-    mut player_q: Query<(&mut Handle<Image>, &Health), With<Player>>,
-    textures: Res<TextureAssets>,
-) {
-    let (mut handle, health) = player_q.get_single_mut().unwrap();
-    let health_percentage = health.health_amount as f32 / health.max_health as f32 * 100.;
-    let next_texture = if health_percentage <= 0. {
-        &textures.boat_crashed
-    } else if health_percentage <= 33. {
-        &textures.boat_dmg2
-    } else if health_percentage <= 66. {
-        &textures.boat_dmg1
-    } else {
-        &textures.boat
-    };
-
-    if *handle != *next_texture {
-        *handle = next_texture.clone();
-    }
-}
-
 fn detect_player_dead(
-    player_health_q: Query<&Health, With<Player>>,
+    mut death_events: EventReader<DeathEvent>,
+    player_q: Query<(), With<Player>>,
     mut state: ResMut<NextState<GameState>>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
-    let player_health = player_health_q.get_single().unwrap();
-    if player_health.health_amount <= 0 {
-        audio.play(audio_assets.boat_crash.clone()).with_volume(0.5);
-        state.set(GameState::End);
+    for death in death_events.iter() {
+        if player_q.contains(death.target) {
+            sound_events.send(SoundEvent::PlayerCrashed);
+            state.set(GameState::End);
+        }
     }
 }
 
 fn move_player_cannon(
-    mut cannon_query: Query<(&mut Transform, &GlobalTransform, &mut PlayerCannon), Without<Player>>,
+    mut cannon_query: Query<(&mut Transform, &mut PlayerCannon), Without<Player>>,
     mut player_query: Query<&Transform, With<Player>>,
-    window: Query<&Window, With<PrimaryWindow>>,
-    camera_q: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-    time: Res<Time>,
+    actions: Res<Actions>,
+    fixed_time: Res<FixedTime>,
 ) {
-    let window = window.get_single().unwrap();
-    let (camera, camera_transform) = camera_q.single();
-    let (mut cannon_transform, global_cannon_transform, mut cannon) =
-        cannon_query.get_single_mut().unwrap();
+    let (mut cannon_transform, mut cannon) = cannon_query.get_single_mut().unwrap();
     let player_transform = player_query.get_single_mut().unwrap();
     let player_rotation = player_transform.rotation;
+    let cannon_translation = player_transform
+        .mul_transform(*cannon_transform)
+        .translation
+        .truncate();
+
+    // Vector where we are pointing at
+    let wishful_vector = actions
+        .player_aim
+        .map(|position| position - cannon_translation)
+        .or(actions.player_aim_direction);
 
-    // check if the cursor is inside the window and get its position
-    // then, ask bevy to convert into world coordinates, and truncate to discard Z
-    if let Some(position) = window
-        .cursor_position()
-        .and_then(|pos| camera.viewport_to_world(camera_transform, pos))
-        .map(|ray| ray.origin.truncate())
-    {
+    if let Some(wishful_vector) = wishful_vector {
         // Apply player rotation to the current cannon rotation
         let current_angle = cannon_transform.rotation.to_euler(EulerRot::YXZ);
         let player_angle = player_rotation.to_euler(EulerRot::YXZ);
 
-        // Vector where we are pointing at
-        let wishful_vector = position - global_cannon_transform.translation().truncate();
         let wishful_vector_normalized = wishful_vector.normalize();
 
         let wishful_angle = wishful_vector_normalized
@@ -270,61 +301,96 @@ fn move_player_cannon(
             } else {
                 1.
             }
+        } else if current_angle.2 >= wishful_rotation_angle.2 {
+            1.
         } else {
-            if current_angle.2 >= wishful_rotation_angle.2 {
-                1.
-            } else {
-                -1.
-            }
+            -1.
         };
 
-        let angle_add = cannon.turn_rate * time.delta_seconds() * angle_sign;
+        let angle_add = cannon.turn_rate * fixed_time.period.as_secs_f32() * angle_sign;
         let next_rotation = Quat::from_rotation_z(current_angle.2 + angle_add);
 
         cannon.vector = angle_to_vector(current_angle.2 + player_angle.2 + angle_add);
         cannon_transform.rotation = next_rotation;
+    } else if actions.player_aim_turn != 0. {
+        // Turning relative to the boat, right turns clockwise
+        let current_angle = cannon_transform.rotation.to_euler(EulerRot::YXZ);
+        let player_angle = player_rotation.to_euler(EulerRot::YXZ);
+        let angle_add = -actions.player_aim_turn.clamp(-1., 1.)
+            * cannon.turn_rate
+            * fixed_time.period.as_secs_f32();
+
+        cannon.vector = angle_to_vector(current_angle.2 + player_angle.2 + angle_add);
+        cannon_transform.rotation = Quat::from_rotation_z(current_angle.2 + angle_add);
     }
 }
 
 fn player_shoot(
     mut commands: Commands,
-    mut player_cannon_q: Query<(&GlobalTransform, &mut PlayerCannon)>,
-    player_q: Query<Entity, With<Player>>,
-    // mouse_input: EventReader<MouseButtonInput>,
-    mouse_input: Res<Input<MouseButton>>,
-    time: Res<Time>,
-    textures: Res<TextureAssets>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut player_cannon_q: Query<(&Transform, &mut PlayerCannon)>,
+    player_q: Query<(Entity, &Transform), With<Player>>,
+    actions: Res<Actions>,
+    balance: Res<Balance>,
+    fixed_time: Res<FixedTime>,
+    mut stats: ResMut<RunStats>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
     let (cannon_transform, mut player_cannon) = player_cannon_q.get_single_mut().unwrap();
-    let player = player_q.get_single().unwrap();
-    player_cannon.timer.tick(time.delta());
-    if mouse_input.pressed(MouseButton::Left) {
-        if player_cannon.timer.finished() {
-            let player_cannon_translation = cannon_transform.translation().truncate();
-            commands
-                .spawn(SpriteBundle {
-                    texture: textures.bullet.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        player_cannon_translation.x,
-                        player_cannon_translation.y,
-                        3.,
-                    )),
-                    ..Default::default()
-                })
-                .insert(Bullet::new(player))
-                .insert(Movement {
-                    vector: player_cannon.vector,
-                    speed: 350.0,
-                    ..Default::default()
-                });
-            player_cannon.timer.reset();
-            audio
-                .play(audio_assets.bullet_fire.clone())
-                .with_volume(0.7);
-        }
+    let (player, player_transform) = player_q.get_single().unwrap();
+    player_cannon.timer.tick(fixed_time.period);
+    if player_cannon.timer.finished() && player_cannon.reloads_left > 0 {
+        player_cannon.reloads_left -= 1;
+        player_cannon.timer.reset();
+    }
+    if !player_cannon.timer.finished() {
+        return;
+    }
+
+    let balance = &balance.player;
+    let angles = if actions.player_fire {
+        vec![0.]
+    } else if actions.player_alt_fire {
+        // Fanned out evenly around where the cannon points
+        let spread = balance.spread_shot_angle.to_radians();
+        let middle = (balance.spread_shot_bullets as f32 - 1.) / 2.;
+        (0..balance.spread_shot_bullets)
+            .map(|bullet| (bullet as f32 - middle) * spread)
+            .collect()
+    } else {
+        return;
+    };
+
+    let player_cannon_translation = player_transform
+        .mul_transform(*cannon_transform)
+        .translation
+        .truncate();
+    for angle in angles.iter() {
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(
+                    player_cannon_translation.x,
+                    player_cannon_translation.y,
+                    3.,
+                )),
+                ..Default::default()
+            })
+            .insert(Bullet::new(player, balance.bullet_damage))
+            .insert(
+                Lifetime::behind_camera(OUT_OF_SIGHT)
+                    .with_max_range(BULLET_RANGE)
+                    .with_max_age(BULLET_MAX_AGE),
+            )
+            .insert(Movement {
+                vector: Vec2::from_angle(*angle).rotate(player_cannon.vector),
+                speed: balance.bullet_speed,
+            });
+    }
+    player_cannon.timer.reset();
+    if actions.player_alt_fire && !actions.player_fire {
+        player_cannon.reloads_left = balance.spread_shot_reloads.saturating_sub(1);
     }
+    stats.shots_fired += angles.len() as u32;
+    sound_events.send(SoundEvent::PlayerShot);
 }
 
 fn angle_to_vector(angle: f32) -> Vec2 {
@@ -1,61 +1,52 @@
 use std::time::Duration;
 
-use bevy::{prelude::*, sprite::collide_aabb::collide};
-use bevy_kira_audio::{Audio, AudioControl};
+use bevy::prelude::*;
 use rand::Rng;
 
 use crate::{
+    audio::SoundEvent,
+    balance::Balance,
+    collision::SpatialGrid,
     environment::Collidable,
-    health::{Health, Mass},
-    loading::{AudioAssets, TextureAssets},
-    menu::MainCamera,
+    health::{DeathEvent, Health, Mass},
+    lifetime::{Lifetime, OUT_OF_SIGHT},
     obstacle::get_random_obstacle_spawn_position,
-    player::{Player, PlayerCannon, PLAYER_SIZE},
-    GameState,
+    player::{Player, PlayerCannon},
+    random::{GameRng, RngStream},
+    spawn::{direct_spawns, SpawnEvent, SpawnKind, Threat},
+    stats::RunStats,
+    GameState, SimulationSet,
 };
 
 pub struct PowerUpPlugin;
 impl Plugin for PowerUpPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<PowerUpSpawnTimers>()
-            .init_resource::<PowerUpExhaustTimers>()
-            .add_system(spawn_power_up_barrels.in_set(OnUpdate(GameState::Playing)))
-            .add_system(detect_dead_barrels.in_set(OnUpdate(GameState::Playing)))
-            .add_system(pick_up_power_ups.in_set(OnUpdate(GameState::Playing)))
-            .add_system(tick_exhaust_timers.in_set(OnUpdate(GameState::Playing)))
+        app.init_resource::<PowerUpExhaustTimers>()
+            .add_system(
+                pick_up_power_ups
+                    .in_set(SimulationSet::Collision)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_systems(
+                (
+                    spawn_power_up_barrels.after(direct_spawns),
+                    detect_dead_barrels.after(spawn_power_up_barrels),
+                    tick_exhaust_timers,
+                )
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
             .add_system(despawn_power_ups.in_schedule(OnEnter(GameState::Restart)));
     }
 }
 
-const BARREL_SIZE: Vec2 = Vec2::new(32., 24.);
-const POWER_UP_SIZES: [bevy::prelude::Vec2; 2] = [Vec2::new(45., 40.), Vec2::new(45., 40.)];
-
 const POWER_UP_KINDS: [PowerUpType; 2] = [PowerUpType::Repair, PowerUpType::Weapon];
 
-#[derive(Resource)]
-pub struct PowerUpSpawnTimers {
-    timers: Vec<Timer>,
-}
-
-impl Default for PowerUpSpawnTimers {
-    fn default() -> Self {
-        PowerUpSpawnTimers {
-            timers: vec![Timer::new(Duration::from_secs(15), TimerMode::Repeating)],
-        }
-    }
-}
-
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct PowerUpExhaustTimers {
     pub weapon: Vec<Timer>,
 }
 
-impl Default for PowerUpExhaustTimers {
-    fn default() -> Self {
-        PowerUpExhaustTimers { weapon: vec![] }
-    }
-}
-
 #[derive(Component)]
 pub struct PowerUpBarrel;
 
@@ -67,85 +58,76 @@ pub enum PowerUpType {
 
 #[derive(Component)]
 pub struct PowerUp {
-    kind: PowerUpType,
+    pub kind: PowerUpType,
 }
 
 fn spawn_power_up_barrels(
     mut commands: Commands,
-    time: Res<Time>,
-    mut spawn_timers: ResMut<PowerUpSpawnTimers>,
-    textures: Res<TextureAssets>,
-    camera_query: Query<&Transform, With<MainCamera>>,
+    mut spawn_events: EventReader<SpawnEvent>,
+    mut game_rng: ResMut<GameRng>,
+    balance: Res<Balance>,
 ) {
-    let camera_position = camera_query.get_single().unwrap().translation.y;
-    let next_spawn_position = camera_position + 600.;
-    for timer in &mut spawn_timers.timers {
-        timer.tick(time.delta());
-        if timer.finished() {
-            let position = get_random_obstacle_spawn_position();
-            commands
-                .spawn(SpriteBundle {
-                    texture: textures.barrel.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        position,
-                        next_spawn_position,
-                        2.,
-                    )),
-                    ..Default::default()
-                })
-                .insert(PowerUpBarrel)
-                .insert(Health {
-                    max_health: 1,
-                    health_amount: 1,
-                    size: BARREL_SIZE,
-                    immune_to_bullets: false,
-                    mass: Mass::Wood,
-                })
-                .insert(Collidable {
-                    size: BARREL_SIZE,
-                    damage: 1,
-                    is_alive: true,
-                });
-            let mut rng = rand::thread_rng();
-            let duration = rng.gen_range(10000..20000);
-            timer.set_duration(Duration::from_millis(duration));
-        }
+    let balance = &balance.power_ups;
+    let rng = game_rng.stream(RngStream::PowerUps);
+    for event in spawn_events
+        .iter()
+        .filter(|event| event.kind == SpawnKind::PowerUpBarrel)
+    {
+        let position = get_random_obstacle_spawn_position(rng);
+        commands
+            .spawn(SpatialBundle {
+                transform: Transform::from_translation(Vec3::new(position, event.y, 2.)),
+                ..Default::default()
+            })
+            .insert(PowerUpBarrel)
+            .insert(Lifetime::behind_camera(OUT_OF_SIGHT))
+            .insert(Threat(event.threat))
+            .insert(Health {
+                max_health: 1,
+                health_amount: 1,
+                shape: balance.barrel_shape,
+                immune_to_bullets: false,
+                mass: Mass::Wood,
+            })
+            .insert(Collidable {
+                shape: balance.barrel_shape,
+                damage: balance.barrel_damage,
+                is_alive: true,
+            });
     }
 }
 
 fn despawn_power_ups(
     mut commands: Commands,
     power_up_q: Query<Entity, Or<(With<PowerUp>, With<PowerUpBarrel>)>>,
-    mut power_up_spawn_timers: ResMut<PowerUpSpawnTimers>,
     mut exhaust_timers: ResMut<PowerUpExhaustTimers>,
 ) {
     for entity in power_up_q.iter() {
         commands.entity(entity).despawn_recursive();
     }
-    *power_up_spawn_timers = PowerUpSpawnTimers::default();
     *exhaust_timers = PowerUpExhaustTimers::default();
 }
 
 fn detect_dead_barrels(
     mut commands: Commands,
-    barrel_q: Query<(Entity, &Transform, &Health), With<PowerUpBarrel>>,
-    textures: Res<TextureAssets>,
+    mut death_events: EventReader<DeathEvent>,
+    barrel_q: Query<&Transform, With<PowerUpBarrel>>,
+    mut game_rng: ResMut<GameRng>,
+    balance: Res<Balance>,
 ) {
-    for (entity, transform, health) in barrel_q.iter() {
-        if health.health_amount <= 0 {
-            let available_power_ups = [
-                textures.power_up_health.clone(),
-                textures.power_up_weapon.clone(),
-            ];
-            let mut rng = rand::thread_rng();
+    let rng = game_rng.stream(RngStream::PowerUps);
+    for death in death_events.iter() {
+        let entity = death.target;
+        if let Ok(transform) = barrel_q.get(entity) {
             let which_one_index = rng.gen_range(0..2);
-            let power_up_texture = &available_power_ups[which_one_index];
-            let size = POWER_UP_SIZES[which_one_index];
             let kind = POWER_UP_KINDS[which_one_index];
+            let shape = match kind {
+                PowerUpType::Repair => balance.power_ups.repair_shape,
+                PowerUpType::Weapon => balance.power_ups.weapon_shape,
+            };
 
             commands
-                .spawn(SpriteBundle {
-                    texture: power_up_texture.clone(),
+                .spawn(SpatialBundle {
                     transform: Transform::from_translation(Vec3::new(
                         transform.translation.x,
                         transform.translation.y,
@@ -154,15 +136,18 @@ fn detect_dead_barrels(
                     ..Default::default()
                 })
                 .insert(PowerUp { kind })
+                .insert(Lifetime::behind_camera(OUT_OF_SIGHT).with_max_age(
+                    Duration::from_secs_f32(balance.power_ups.dropped_lifetime_secs),
+                ))
                 .insert(Health {
                     max_health: 1,
                     health_amount: 1,
-                    size,
+                    shape,
                     immune_to_bullets: true,
                     mass: Mass::Wood,
                 })
                 .insert(Collidable {
-                    size,
+                    shape,
                     damage: 0,
                     is_alive: true,
                 });
@@ -176,41 +161,48 @@ fn pick_up_power_ups(
     mut commands: Commands,
     mut player_q: Query<(&Transform, &mut Health), With<Player>>,
     mut player_cannon_q: Query<&mut PlayerCannon>,
-    power_ups_q: Query<(Entity, &Transform, &Collidable, &PowerUp), Without<Player>>,
+    power_ups_q: Query<(&Transform, &Collidable, &PowerUp), Without<Player>>,
     mut power_ups_exhaust_timers: ResMut<PowerUpExhaustTimers>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    balance: Res<Balance>,
+    grid: Res<SpatialGrid>,
+    mut stats: ResMut<RunStats>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
+    let balance = &balance.power_ups;
     let (player_transform, mut player_health) = player_q.get_single_mut().unwrap();
     let mut player_cannon = player_cannon_q.get_single_mut().unwrap();
-    for (entity, transform, collidable, power_up) in power_ups_q.iter() {
-        let collision = collide(
-            player_transform.translation,
-            PLAYER_SIZE,
-            transform.translation,
-            collidable.size,
-        );
-        if Option::is_some(&collision) {
+    let player_shape = player_health.shape;
+    let nearby = grid.query(
+        player_transform.translation.truncate(),
+        player_shape.bounds(player_transform),
+    );
+    for entity in nearby {
+        let Ok((transform, collidable, power_up)) = power_ups_q.get(entity) else {
+            continue;
+        };
+        if player_shape.intersects(player_transform, &collidable.shape, transform) {
             match power_up.kind {
                 PowerUpType::Repair => {
-                    player_health.max_health += 1;
-                    player_health.health_amount =
-                        (player_health.health_amount + 3).min(player_health.max_health);
-                    audio.play(audio_assets.repair.clone()).with_volume(0.4);
+                    player_health.max_health += balance.repair_max_health;
+                    player_health.health_amount = (player_health.health_amount
+                        + balance.repair_health)
+                        .min(player_health.max_health);
+                    stats.repairs_collected += 1;
+                    sound_events.send(SoundEvent::Repair);
                 }
                 PowerUpType::Weapon => {
                     let current_timer_duration = player_cannon.timer.duration().as_millis();
                     let current_turn_rate = player_cannon.turn_rate;
                     player_cannon.timer.set_duration(Duration::from_millis(
-                        (current_timer_duration as f32 * 0.75) as u64,
+                        (current_timer_duration as f32 * (1. - balance.weapon_boost)) as u64,
+                    ));
+                    player_cannon.turn_rate = current_turn_rate * (1. + balance.weapon_boost);
+                    power_ups_exhaust_timers.weapon.push(Timer::from_seconds(
+                        balance.weapon_duration_secs,
+                        TimerMode::Once,
                     ));
-                    player_cannon.turn_rate = current_turn_rate * 1.25;
-                    power_ups_exhaust_timers
-                        .weapon
-                        .push(Timer::new(Duration::from_secs(7), TimerMode::Once));
-                    audio
-                        .play(audio_assets.power_up_weapon.clone())
-                        .with_volume(0.7);
+                    stats.weapon_boosts_collected += 1;
+                    sound_events.send(SoundEvent::WeaponPowerUp);
                 }
             }
             commands.entity(entity).despawn();
@@ -221,26 +213,25 @@ fn pick_up_power_ups(
 fn tick_exhaust_timers(
     mut exhaust_timers: ResMut<PowerUpExhaustTimers>,
     mut player_cannon_q: Query<&mut PlayerCannon>,
-    time: Res<Time>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    fixed_time: Res<FixedTime>,
+    balance: Res<Balance>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
+    let weapon_boost = balance.power_ups.weapon_boost;
     let mut player_cannon = player_cannon_q.get_single_mut().unwrap();
     for timer in exhaust_timers.weapon.iter_mut() {
-        timer.tick(time.delta());
+        timer.tick(fixed_time.period);
         if timer.finished() {
             let current_timer_duration = player_cannon.timer.duration().as_millis();
             let current_turn_rate = player_cannon.turn_rate;
             // Side-effect of picking up power up is that it will improve the weapon over time
             // even when power up is not active anymore
-            // Mathematically upgrading by 25% and then downgrading by 25% will not equal 100%
+            // Mathematically upgrading by the boost and then downgrading by it will not equal 100%
             player_cannon.timer.set_duration(Duration::from_millis(
-                (current_timer_duration as f32 * 1.25) as u64,
+                (current_timer_duration as f32 * (1. + weapon_boost)) as u64,
             ));
-            player_cannon.turn_rate = current_turn_rate * 0.75;
-            audio
-                .play(audio_assets.power_up_weapon_exhaust.clone())
-                .with_volume(0.7);
+            player_cannon.turn_rate = current_turn_rate * (1. - weapon_boost);
+            sound_events.send(SoundEvent::WeaponPowerUpExhausted);
         }
     }
     exhaust_timers.weapon.retain(|timer| !timer.finished());
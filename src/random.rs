@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::{daily::DailyChallenge, GameState};
+
+pub struct RandomPlugin;
+
+/// This plugin owns the only source of randomness of the simulation.
+/// Each run is seeded once when it starts, so the same seed reproduces the same
+/// river, enemies and drops.
+impl Plugin for RandomPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FixedSeed>()
+            .insert_resource(GameRng::new(0))
+            .add_system(seed_run.in_schedule(OnEnter(GameState::Init)));
+    }
+}
+
+/// Seed used for every run, a fresh random seed is drawn per run when neither this nor a [`DailyChallenge`] is set
+#[derive(Resource, Default)]
+pub struct FixedSeed(pub Option<u64>);
+
+/// Independent random number streams.
+/// Every subsystem draws from its own stream so that e.g. enemies spawning
+/// can never change how the river looks.
+#[derive(Clone, Copy)]
+pub enum RngStream {
+    Environment,
+    Obstacles,
+    Enemies,
+    PowerUps,
+    Spawns,
+}
+
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    environment: ChaCha8Rng,
+    obstacles: ChaCha8Rng,
+    enemies: ChaCha8Rng,
+    power_ups: ChaCha8Rng,
+    spawns: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            environment: Self::seeded_stream(seed, RngStream::Environment),
+            obstacles: Self::seeded_stream(seed, RngStream::Obstacles),
+            enemies: Self::seeded_stream(seed, RngStream::Enemies),
+            power_ups: Self::seeded_stream(seed, RngStream::PowerUps),
+            spawns: Self::seeded_stream(seed, RngStream::Spawns),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        match stream {
+            RngStream::Environment => &mut self.environment,
+            RngStream::Obstacles => &mut self.obstacles,
+            RngStream::Enemies => &mut self.enemies,
+            RngStream::PowerUps => &mut self.power_ups,
+            RngStream::Spawns => &mut self.spawns,
+        }
+    }
+
+    /// One of several independent streams of a subsystem, e.g. one for every spawn wave
+    pub fn indexed_stream(&self, stream: RngStream, index: u64) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        // Above the streams of the subsystems themselves
+        rng.set_stream(((index + 1) << 8) | stream as u64);
+        rng
+    }
+
+    fn seeded_stream(seed: u64, stream: RngStream) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        rng.set_stream(stream as u64);
+        rng
+    }
+}
+
+pub fn seed_run(
+    mut rng: ResMut<GameRng>,
+    fixed_seed: Res<FixedSeed>,
+    challenge: Res<DailyChallenge>,
+) {
+    let seed = fixed_seed
+        .0
+        .or(challenge.seed())
+        .unwrap_or_else(rand::random);
+    info!("Starting run with seed {}", seed);
+    *rng = GameRng::new(seed);
+}
@@ -0,0 +1,464 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+
+use crate::actions::Actions;
+use crate::difficulty::DifficultyPreset;
+use crate::random::{seed_run, FixedSeed, GameRng};
+use crate::score::{start_score, GameScore};
+use crate::{GameState, SimulationSet};
+
+pub struct ReplayPlugin;
+
+/// This plugin records the player's actions of every simulation tick
+/// and can feed a recorded run back instead of live input.
+/// Together with the seed and difficulty preset stored in the recording the run plays out exactly the same again
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayOutput>()
+            .init_resource::<Recording>()
+            .init_resource::<Playback>()
+            .add_system(
+                use_replay_settings
+                    .after(start_score)
+                    .before(seed_run)
+                    .in_schedule(OnEnter(GameState::Init)),
+            )
+            .add_system(
+                start_recording
+                    .after(seed_run)
+                    .in_schedule(OnEnter(GameState::Init)),
+            )
+            .add_systems(
+                (
+                    play_back_actions.run_if(is_playing_back),
+                    record_actions.after(play_back_actions),
+                )
+                    .in_set(SimulationSet::Actions)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(save_recording.in_schedule(OnExit(GameState::Playing)));
+    }
+}
+
+const REPLAY_MAGIC: &[u8; 4] = b"GERP";
+/// Version 2 added the difficulty preset, version 1 replays were all played on normal.
+/// Version 3 added turning the cannon without an aim position,
+/// version 4 the aim direction and the alternative fire
+const REPLAY_VERSION: u8 = 4;
+/// Frames reserved up front when reading, longer replays grow as they are read
+const MAX_PREALLOCATED_FRAMES: usize = 1 << 16;
+
+const HAS_MOVEMENT: u8 = 1;
+const HAS_AIM: u8 = 1 << 1;
+const FIRE: u8 = 1 << 2;
+const HAS_AIM_TURN: u8 = 1 << 3;
+const HAS_AIM_DIRECTION: u8 = 1 << 4;
+const ALT_FIRE: u8 = 1 << 5;
+
+/// What the player did during one simulation tick
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct ReplayFrame {
+    pub movement: Option<Vec2>,
+    pub aim: Option<Vec2>,
+    pub aim_direction: Option<Vec2>,
+    pub aim_turn: f32,
+    pub fire: bool,
+    pub alt_fire: bool,
+}
+
+impl From<&Actions> for ReplayFrame {
+    fn from(actions: &Actions) -> Self {
+        ReplayFrame {
+            movement: actions.player_movement,
+            aim: actions.player_aim,
+            aim_direction: actions.player_aim_direction,
+            aim_turn: actions.player_aim_turn,
+            fire: actions.player_fire,
+            alt_fire: actions.player_alt_fire,
+        }
+    }
+}
+
+impl ReplayFrame {
+    fn apply(&self, actions: &mut Actions) {
+        actions.player_movement = self.movement;
+        actions.player_aim = self.aim;
+        actions.player_aim_direction = self.aim_direction;
+        actions.player_aim_turn = self.aim_turn;
+        actions.player_fire = self.fire;
+        actions.player_alt_fire = self.alt_fire;
+    }
+}
+
+/// A recorded run: its seed, difficulty preset and the player's actions for every simulation tick.
+///
+/// On disk the frames are run-length encoded, ticks where nothing changed only cost a counter.
+#[derive(Clone, Default, Debug)]
+pub struct Replay {
+    pub seed: u64,
+    pub preset: DifficultyPreset,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(seed: u64, preset: DifficultyPreset) -> Self {
+        Replay {
+            seed,
+            preset,
+            frames: vec![],
+        }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Replay> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(REPLAY_MAGIC)?;
+        writer.write_all(&[REPLAY_VERSION])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&[self.preset as u8])?;
+        writer.write_all(&(self.frames.len() as u32).to_le_bytes())?;
+
+        let mut frames = self.frames.iter().peekable();
+        while let Some(frame) = frames.next() {
+            let mut repeat: u16 = 1;
+            while repeat < u16::MAX && frames.peek() == Some(&frame) {
+                frames.next();
+                repeat += 1;
+            }
+            writer.write_all(&repeat.to_le_bytes())?;
+
+            let mut flags = 0;
+            if frame.movement.is_some() {
+                flags |= HAS_MOVEMENT;
+            }
+            if frame.aim.is_some() {
+                flags |= HAS_AIM;
+            }
+            if frame.fire {
+                flags |= FIRE;
+            }
+            if frame.aim_turn != 0. {
+                flags |= HAS_AIM_TURN;
+            }
+            if frame.aim_direction.is_some() {
+                flags |= HAS_AIM_DIRECTION;
+            }
+            if frame.alt_fire {
+                flags |= ALT_FIRE;
+            }
+            writer.write_all(&[flags])?;
+            for vector in [frame.movement, frame.aim, frame.aim_direction]
+                .into_iter()
+                .flatten()
+            {
+                writer.write_all(&vector.x.to_le_bytes())?;
+                writer.write_all(&vector.y.to_le_bytes())?;
+            }
+            if frame.aim_turn != 0. {
+                writer.write_all(&frame.aim_turn.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> io::Result<Replay> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != REPLAY_MAGIC {
+            return Err(invalid_data("not a replay file"));
+        }
+        let [version] = read_bytes(&mut reader)?;
+        if version == 0 || version > REPLAY_VERSION {
+            return Err(invalid_data(&format!(
+                "unsupported replay version {}",
+                version
+            )));
+        }
+        let seed = u64::from_le_bytes(read_bytes(&mut reader)?);
+        let preset = if version >= 2 {
+            let [preset] = read_bytes(&mut reader)?;
+            *DifficultyPreset::ALL
+                .get(preset as usize)
+                .ok_or_else(|| invalid_data("unknown difficulty preset"))?
+        } else {
+            DifficultyPreset::Normal
+        };
+        let frame_count = u32::from_le_bytes(read_bytes(&mut reader)?) as usize;
+
+        // A corrupt count must not allocate gigabytes before the frames prove it wrong
+        let mut frames = Vec::with_capacity(frame_count.min(MAX_PREALLOCATED_FRAMES));
+        while frames.len() < frame_count {
+            let repeat = u16::from_le_bytes(read_bytes(&mut reader)?) as usize;
+            let [flags] = read_bytes(&mut reader)?;
+            let frame = ReplayFrame {
+                movement: if flags & HAS_MOVEMENT != 0 {
+                    Some(read_vector(&mut reader)?)
+                } else {
+                    None
+                },
+                aim: if flags & HAS_AIM != 0 {
+                    Some(read_vector(&mut reader)?)
+                } else {
+                    None
+                },
+                aim_direction: if flags & HAS_AIM_DIRECTION != 0 {
+                    Some(read_vector(&mut reader)?)
+                } else {
+                    None
+                },
+                aim_turn: if flags & HAS_AIM_TURN != 0 {
+                    f32::from_le_bytes(read_bytes(&mut reader)?)
+                } else {
+                    0.
+                },
+                fire: flags & FIRE != 0,
+                alt_fire: flags & ALT_FIRE != 0,
+            };
+            if repeat == 0 || frames.len() + repeat > frame_count {
+                return Err(invalid_data("corrupted replay frames"));
+            }
+            frames.resize(frames.len() + repeat, frame);
+        }
+        Ok(Replay {
+            seed,
+            preset,
+            frames,
+        })
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_vector(reader: &mut impl Read) -> io::Result<Vec2> {
+    Ok(Vec2::new(
+        f32::from_le_bytes(read_bytes(reader)?),
+        f32::from_le_bytes(read_bytes(reader)?),
+    ))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// File the recording of a run is saved to whenever the run stops (it ends or gets paused).
+/// Nothing is saved when this is not set
+#[derive(Resource, Default)]
+pub struct ReplayOutput(pub Option<PathBuf>);
+
+/// Recording of the current run
+#[derive(Resource, Default)]
+pub struct Recording(Replay);
+
+impl Recording {
+    pub fn replay(&self) -> &Replay {
+        &self.0
+    }
+}
+
+/// Replay to feed into the simulation instead of live input
+#[derive(Resource, Default)]
+pub struct Playback {
+    replay: Option<Replay>,
+    next_frame: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Playback {
+            replay: Some(replay),
+            next_frame: 0,
+        }
+    }
+}
+
+/// Live input is ignored while a replay is being played back
+pub fn is_playing_back(playback: Res<Playback>) -> bool {
+    playback.replay.is_some()
+}
+
+/// Runs before anything of the run is set up, so the replay is played on the settings it was recorded with
+pub fn use_replay_settings(
+    playback: Res<Playback>,
+    mut fixed_seed: ResMut<FixedSeed>,
+    mut game_score: ResMut<GameScore>,
+) {
+    if let Some(replay) = &playback.replay {
+        fixed_seed.0 = Some(replay.seed);
+        game_score.preset = replay.preset;
+    }
+}
+
+fn start_recording(
+    rng: Res<GameRng>,
+    game_score: Res<GameScore>,
+    mut recording: ResMut<Recording>,
+    mut playback: ResMut<Playback>,
+) {
+    recording.0 = Replay::new(rng.seed(), game_score.preset);
+    playback.next_frame = 0;
+}
+
+fn play_back_actions(mut playback: ResMut<Playback>, mut actions: ResMut<Actions>) {
+    let next_frame = playback.next_frame;
+    let Some(replay) = &playback.replay else {
+        return;
+    };
+    match replay.frames.get(next_frame) {
+        Some(frame) => frame.apply(&mut actions),
+        None => {
+            if next_frame == replay.frames.len() {
+                info!("Replay finished after {} ticks", next_frame);
+            }
+            *actions = Actions::default();
+        }
+    }
+    playback.next_frame += 1;
+}
+
+fn record_actions(actions: Res<Actions>, mut recording: ResMut<Recording>) {
+    recording.0.frames.push(ReplayFrame::from(&*actions));
+}
+
+fn save_recording(recording: Res<Recording>, output: Res<ReplayOutput>) {
+    let Some(path) = &output.0 else {
+        return;
+    };
+    match recording.0.save(path) {
+        Ok(()) => info!(
+            "Saved replay of {} ticks to {}",
+            recording.0.frames.len(),
+            path.display()
+        ),
+        Err(error) => error!("Could not save replay to {}: {}", path.display(), error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(replay: &Replay) -> Vec<u8> {
+        let mut bytes = vec![];
+        replay.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn replay_survives_a_round_trip() {
+        let idle = ReplayFrame::default();
+        let steering = ReplayFrame {
+            movement: Some(Vec2::new(-1., 0.5)),
+            aim: Some(Vec2::new(120., 640.)),
+            fire: true,
+            ..default()
+        };
+        let turning = ReplayFrame {
+            aim_direction: Some(Vec2::new(0., 1.)),
+            aim_turn: -0.5,
+            alt_fire: true,
+            ..default()
+        };
+        let replay = Replay {
+            seed: 0xdead_beef_1234,
+            preset: DifficultyPreset::Nightmare,
+            frames: vec![idle, idle, idle, steering, turning, turning, idle],
+        };
+
+        let decoded = Replay::read_from(encode(&replay).as_slice()).unwrap();
+
+        assert_eq!(decoded.seed, replay.seed);
+        assert_eq!(decoded.preset, replay.preset);
+        assert_eq!(decoded.frames, replay.frames);
+    }
+
+    #[test]
+    fn repeated_frames_are_stored_once() {
+        let mut replay = Replay::new(7, DifficultyPreset::Normal);
+        replay.frames = vec![ReplayFrame::default(); 1000];
+
+        // Header, then a single repeat counter and flags
+        assert_eq!(encode(&replay).len(), 4 + 1 + 8 + 1 + 4 + 2 + 1);
+    }
+
+    #[test]
+    fn version_1_replays_are_played_on_normal() {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.push(1);
+        bytes.extend(42u64.to_le_bytes());
+        bytes.extend(3u32.to_le_bytes());
+        // Two ticks steering right and firing
+        bytes.extend(2u16.to_le_bytes());
+        bytes.push(HAS_MOVEMENT | FIRE);
+        bytes.extend(1f32.to_le_bytes());
+        bytes.extend(0f32.to_le_bytes());
+        // One tick aiming
+        bytes.extend(1u16.to_le_bytes());
+        bytes.push(HAS_AIM);
+        bytes.extend(10f32.to_le_bytes());
+        bytes.extend(20f32.to_le_bytes());
+
+        let replay = Replay::read_from(bytes.as_slice()).unwrap();
+
+        assert_eq!(replay.seed, 42);
+        assert_eq!(replay.preset, DifficultyPreset::Normal);
+        let steering = ReplayFrame {
+            movement: Some(Vec2::new(1., 0.)),
+            fire: true,
+            ..default()
+        };
+        let aiming = ReplayFrame {
+            aim: Some(Vec2::new(10., 20.)),
+            ..default()
+        };
+        assert_eq!(replay.frames, vec![steering, steering, aiming]);
+    }
+
+    #[test]
+    fn unknown_files_are_rejected() {
+        let mut newer = encode(&Replay::new(1, DifficultyPreset::Easy));
+        newer[4] = REPLAY_VERSION + 1;
+        assert!(Replay::read_from(newer.as_slice()).is_err());
+
+        assert!(Replay::read_from(&b"RIFF\x01"[..]).is_err());
+    }
+
+    #[test]
+    fn frames_beyond_the_count_are_rejected() {
+        let mut bytes = encode(&Replay {
+            seed: 1,
+            preset: DifficultyPreset::Normal,
+            frames: vec![ReplayFrame::default(); 2],
+        });
+        // The only run of frames claims to repeat three times
+        let repeat_at = bytes.len() - 3;
+        bytes[repeat_at..repeat_at + 2].copy_from_slice(&3u16.to_le_bytes());
+        assert!(Replay::read_from(bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn truncated_replays_with_a_huge_frame_count_are_rejected() {
+        let mut bytes = REPLAY_MAGIC.to_vec();
+        bytes.push(REPLAY_VERSION);
+        bytes.extend(1u64.to_le_bytes());
+        bytes.push(0);
+        bytes.extend(u32::MAX.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.push(FIRE);
+        assert!(Replay::read_from(bytes.as_slice()).is_err());
+    }
+}
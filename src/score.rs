@@ -1,14 +1,25 @@
 use bevy::{prelude::*, time::Stopwatch};
 
-use crate::{environment::MAP_HEIGHT, player::Player, GameState};
+use crate::{
+    balance::DifficultyBalance, difficulty::DifficultyPreset, environment::MAP_HEIGHT,
+    player::Player, random::seed_run, GameState, SimulationSet,
+};
 
 pub struct ScorePlugin;
 
 impl Plugin for ScorePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<GameScore>()
-            .add_system(update_timer.in_set(OnUpdate(GameState::Playing)))
-            .add_system(update_distance.in_set(OnUpdate(GameState::Playing)))
+            .add_system(
+                start_score
+                    .before(seed_run)
+                    .in_schedule(OnEnter(GameState::Init)),
+            )
+            .add_systems(
+                (update_timer, update_distance)
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
             .add_system(restart_score.in_schedule(OnEnter(GameState::Restart)));
     }
 }
@@ -18,6 +29,8 @@ pub struct GameScore {
     pub elapsed_time: Stopwatch,
     pub score: i32,
     pub distance_traveled: f32,
+    /// Difficulty preset the run is played on
+    pub preset: DifficultyPreset,
 }
 
 impl Default for GameScore {
@@ -26,15 +39,31 @@ impl Default for GameScore {
             elapsed_time: Stopwatch::new(),
             score: 0,
             distance_traveled: 0.,
+            preset: DifficultyPreset::default(),
         }
     }
 }
 
-fn update_timer(mut game_score: ResMut<GameScore>, time: Res<Time>) {
-    game_score.elapsed_time.tick(time.delta());
+impl GameScore {
+    /// What the run is ranked by: the points plus one for every `distance_per_point` meters traveled
+    pub fn final_score(&self, balance: &DifficultyBalance) -> i32 {
+        self.score + self.distance_traveled as i32 / balance.distance_per_point
+    }
+}
+
+/// The run starts on the preset picked in the menu, a replay or the daily challenge may override it
+pub fn start_score(mut game_score: ResMut<GameScore>, preset: Res<DifficultyPreset>) {
+    game_score.preset = *preset;
+}
+
+fn update_timer(mut game_score: ResMut<GameScore>, fixed_time: Res<FixedTime>) {
+    game_score.elapsed_time.tick(fixed_time.period);
 }
 
-fn update_distance(mut game_score: ResMut<GameScore>, player_q: Query<&Transform, With<Player>>) {
+pub fn update_distance(
+    mut game_score: ResMut<GameScore>,
+    player_q: Query<&Transform, With<Player>>,
+) {
     let start_y = MAP_HEIGHT / 2.;
     let distance_in_world = player_q.get_single().unwrap().translation.y - start_y;
     game_score.distance_traveled = distance_in_world / 16.;
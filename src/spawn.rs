@@ -0,0 +1,152 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use rand::Rng;
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+
+use crate::{
+    balance::{Balance, SpawnEntry},
+    camera::MainCamera,
+    difficulty::{Difficulty, DifficultyLevel},
+    environment::MAP_HEIGHT,
+    health::Health,
+    random::{seed_run, GameRng, RngStream},
+    score::{update_distance, GameScore},
+    GameState, SimulationSet,
+};
+
+pub struct SpawnPlugin;
+
+/// This plugin decides what appears on the river and when.
+/// It rolls the spawn table of the [`Balance`] once per wave and keeps the threat on screen
+/// within budget, the plugins owning the kinds do the actual spawning on a [`SpawnEvent`]
+impl Plugin for SpawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpawnDirector>()
+            .add_event::<SpawnEvent>()
+            .add_system(
+                reset_director
+                    .after(seed_run)
+                    .in_schedule(OnEnter(GameState::Init)),
+            )
+            .add_system(
+                direct_spawns
+                    // Entries unlock at the distance the boat is at after this tick
+                    .after(update_distance)
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            );
+    }
+}
+
+/// Everything that appears on the river by itself
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpawnKind {
+    Obstacle,
+    SideCannon,
+    Pirate,
+    PowerUpBarrel,
+}
+
+/// Something of the given kind has to be spawned this tick
+pub struct SpawnEvent {
+    pub kind: SpawnKind,
+    /// Where along the river it appears
+    pub y: f32,
+    pub threat: f32,
+}
+
+/// How dangerous the entity is while it is alive and not yet left behind
+#[derive(Component)]
+pub struct Threat(pub f32);
+
+/// Timers and random numbers of the spawn waves, in the order of the waves in the [`Balance`]
+#[derive(Resource, Default)]
+pub struct SpawnDirector {
+    waves: Vec<(Timer, ChaCha8Rng)>,
+}
+
+fn reset_director(
+    mut director: ResMut<SpawnDirector>,
+    game_rng: Res<GameRng>,
+    balance: Res<Balance>,
+) {
+    // Every wave draws from its own stream and the same amount every time it comes,
+    // so when the player keeps one wave from spawning the others still roll the same as for everyone else
+    director.waves = balance
+        .spawns
+        .waves
+        .iter()
+        .enumerate()
+        .map(|(index, wave)| {
+            (
+                Timer::from_seconds(wave.first_delay_secs, TimerMode::Repeating),
+                game_rng.indexed_stream(RngStream::Spawns, index as u64),
+            )
+        })
+        .collect();
+}
+
+pub fn direct_spawns(
+    mut director: ResMut<SpawnDirector>,
+    fixed_time: Res<FixedTime>,
+    difficulty: Res<State<Difficulty>>,
+    level: Res<DifficultyLevel>,
+    game_score: Res<GameScore>,
+    camera_query: Query<&Transform, With<MainCamera>>,
+    threats_q: Query<(&Transform, &Threat, &Health)>,
+    balance: Res<Balance>,
+    mut spawn_events: EventWriter<SpawnEvent>,
+) {
+    let spawn_rate = balance.presets.get(game_score.preset).spawn_rate_multiplier;
+    let growth = &balance.difficulty;
+    let balance = &balance.spawns;
+    let camera_position = camera_query.get_single().unwrap().translation.y;
+    // Everything that has not left the bottom of the screen yet
+    let mut threat_on_screen: f32 = threats_q
+        .iter()
+        .filter(|(transform, _, health)| {
+            health.health_amount > 0 && transform.translation.y >= camera_position - MAP_HEIGHT / 2.
+        })
+        .map(|(_, threat, _)| threat.0)
+        .sum();
+
+    for (wave, (timer, rng)) in balance.waves.iter().zip(director.waves.iter_mut()) {
+        if difficulty.0 < wave.from_difficulty {
+            continue;
+        }
+        timer.tick(fixed_time.period);
+        if !timer.finished() {
+            continue;
+        }
+        let duration = Duration::from_millis(rng.gen_range(wave.interval_ms.clone()));
+        timer.set_duration(duration.div_f32(level.scale(growth.spawn_rate_growth) * spawn_rate));
+
+        let available: Vec<&SpawnEntry> = balance
+            .table
+            .iter()
+            .filter(|entry| {
+                difficulty.0 >= entry.from_difficulty
+                    && game_score.distance_traveled >= entry.from_distance
+                    && threat_on_screen + entry.threat <= balance.threat_budget
+            })
+            .collect();
+        // Rolled even when nothing fits the budget, the wave has to draw the same either way
+        let roll: f32 = rng.gen();
+        let total_weight: u32 = available.iter().map(|entry| entry.weight).sum();
+        let mut target = roll * total_weight as f32;
+        let Some(entry) = available.into_iter().find(|entry| {
+            target -= entry.weight as f32;
+            target < 0.
+        }) else {
+            continue;
+        };
+        threat_on_screen += entry.threat;
+        spawn_events.send(SpawnEvent {
+            kind: entry.kind,
+            y: camera_position + entry.ahead,
+            threat: entry.threat,
+        });
+    }
+}
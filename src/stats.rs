@@ -0,0 +1,152 @@
+use bevy::prelude::*;
+
+use crate::{
+    enemy::{Enemy, EnemyPirate},
+    environment::LandTile,
+    health::{DamageEvent, DamageKind, DeathEvent, Health, HurtEvent, Mass},
+    obstacle::ObstacleTile,
+    player::Player,
+    power_up::PowerUpBarrel,
+    GameState, SimulationSet,
+};
+
+pub struct StatsPlugin;
+
+/// This plugin keeps count of what happened during a run for the breakdown at its end.
+/// Everything that has an event is counted here, shots and picked up power ups are counted where they happen
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RunStats>()
+            .add_systems(
+                (count_hits, count_kills, count_damage_taken)
+                    .in_set(SimulationSet::Logic)
+                    .in_schedule(CoreSchedule::FixedUpdate),
+            )
+            .add_system(restart_stats.in_schedule(OnEnter(GameState::Restart)));
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct RunStats {
+    pub shots_fired: u32,
+    /// Shots of the player that hit something
+    pub hits: u32,
+    pub side_cannons_destroyed: u32,
+    pub pirates_destroyed: u32,
+    pub logs_broken: u32,
+    pub barrels_opened: u32,
+    pub repairs_collected: u32,
+    pub weapon_boosts_collected: u32,
+    pub damage_taken: DamageTaken,
+    /// Lowest health the boat survived a hit with
+    pub closest_call: Option<i32>,
+}
+
+impl RunStats {
+    /// Share of the shots that hit something, none before the first shot
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.shots_fired > 0).then(|| self.hits as f32 / self.shots_fired as f32)
+    }
+}
+
+/// Health the boat lost to each kind of source
+#[derive(Default, Debug)]
+pub struct DamageTaken {
+    pub obstacles: i32,
+    /// Running aground on the river banks
+    pub land: i32,
+    pub barrels: i32,
+    pub side_cannons: i32,
+    pub pirates: i32,
+    /// Bullets of enemies that were gone by the time their bullet hit
+    pub other: i32,
+}
+
+impl DamageTaken {
+    pub fn total(&self) -> i32 {
+        self.obstacles + self.land + self.barrels + self.side_cannons + self.pirates + self.other
+    }
+}
+
+fn count_hits(
+    mut damage_events: EventReader<DamageEvent>,
+    player_q: Query<(), With<Player>>,
+    mut stats: ResMut<RunStats>,
+) {
+    for damage in damage_events.iter() {
+        if damage.kind == DamageKind::Bullet && player_q.contains(damage.source) {
+            stats.hits += 1;
+        }
+    }
+}
+
+fn count_kills(
+    mut death_events: EventReader<DeathEvent>,
+    enemies_q: Query<(), With<Enemy>>,
+    pirates_q: Query<(), With<EnemyPirate>>,
+    obstacles_q: Query<&Health, With<ObstacleTile>>,
+    barrels_q: Query<(), With<PowerUpBarrel>>,
+    mut stats: ResMut<RunStats>,
+) {
+    for death in death_events.iter() {
+        if enemies_q.contains(death.target) {
+            stats.side_cannons_destroyed += 1;
+        } else if pirates_q.contains(death.target) {
+            stats.pirates_destroyed += 1;
+        } else if obstacles_q
+            .get(death.target)
+            .is_ok_and(|health| matches!(health.mass, Mass::Wood))
+        {
+            // Rocks can be worn down too, they are no logs though
+            stats.logs_broken += 1;
+        } else if barrels_q.contains(death.target) {
+            stats.barrels_opened += 1;
+        }
+    }
+}
+
+fn count_damage_taken(
+    mut hurt_events: EventReader<HurtEvent>,
+    player_q: Query<&Health, With<Player>>,
+    enemies_q: Query<(), With<Enemy>>,
+    pirates_q: Query<(), With<EnemyPirate>>,
+    obstacles_q: Query<(), With<ObstacleTile>>,
+    land_q: Query<(), With<LandTile>>,
+    barrels_q: Query<(), With<PowerUpBarrel>>,
+    mut stats: ResMut<RunStats>,
+) {
+    let mut hurt = false;
+    for hurt_event in hurt_events.iter() {
+        if !player_q.contains(hurt_event.target) {
+            continue;
+        }
+        hurt = true;
+        let source = hurt_event.source;
+        let damage_taken = &mut stats.damage_taken;
+        let taken = if obstacles_q.contains(source) {
+            &mut damage_taken.obstacles
+        } else if land_q.contains(source) {
+            &mut damage_taken.land
+        } else if barrels_q.contains(source) {
+            &mut damage_taken.barrels
+        } else if enemies_q.contains(source) {
+            &mut damage_taken.side_cannons
+        } else if pirates_q.contains(source) {
+            &mut damage_taken.pirates
+        } else {
+            &mut damage_taken.other
+        };
+        *taken += hurt_event.amount;
+    }
+    let Ok(health) = player_q.get_single() else {
+        return;
+    };
+    if hurt && health.health_amount > 0 {
+        let closest_call = stats.closest_call.get_or_insert(health.health_amount);
+        *closest_call = (*closest_call).min(health.health_amount);
+    }
+}
+
+fn restart_stats(mut stats: ResMut<RunStats>) {
+    *stats = RunStats::default();
+}
@@ -0,0 +1,75 @@
+//! What the game remembers between sessions, stored as text under a name.
+//! Native builds keep a file per name in the platform's data directory,
+//! the web build keeps them in the local storage of the page.
+
+pub use platform::{now, read, write};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn path(name: &str) -> Option<PathBuf> {
+        let data_dir = if cfg!(target_os = "windows") {
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        } else if cfg!(target_os = "macos") {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join("Library/Application Support"))
+        } else {
+            std::env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+                .or_else(|| {
+                    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+                })
+        };
+        Some(data_dir?.join("grand_escape").join(format!("{}.ron", name)))
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        std::fs::read_to_string(path(name)?).ok()
+    }
+
+    pub fn write(name: &str, content: &str) -> Result<(), String> {
+        let path = path(name).ok_or("no data directory")?;
+        if let Some(directory) = path.parent() {
+            std::fs::create_dir_all(directory).map_err(|error| error.to_string())?;
+        }
+        std::fs::write(&path, content).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    /// Seconds since the unix epoch
+    pub fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn key(name: &str) -> String {
+        format!("grand_escape.{}", name)
+    }
+
+    pub fn read(name: &str) -> Option<String> {
+        local_storage()?.get_item(&key(name)).ok()?
+    }
+
+    pub fn write(name: &str, content: &str) -> Result<(), String> {
+        local_storage()
+            .ok_or("no local storage")?
+            .set_item(&key(name), content)
+            .map_err(|error| format!("{:?}", error))
+    }
+
+    /// Seconds since the unix epoch
+    pub fn now() -> u64 {
+        (js_sys::Date::now() / 1000.) as u64
+    }
+}
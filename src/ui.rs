@@ -1,10 +1,17 @@
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
 
 use crate::{
+    actions::{TouchButton, TouchControls},
+    balance::Balance,
+    daily::DailyChallenge,
+    ghost::GhostGap,
     health::Health,
+    high_scores::format_date,
     loading::{FontAssets, TextureAssets},
-    player::Player,
+    player::{Movement, Player},
     power_up::PowerUpExhaustTimers,
+    random::seed_run,
     score::GameScore,
     GameState,
 };
@@ -13,11 +20,19 @@ pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(setup_ui.in_schedule(OnEnter(GameState::Init)))
-            .add_system(despawn_ui.in_schedule(OnEnter(GameState::Restart)))
-            .add_system(update_score.in_set(OnUpdate(GameState::Playing)))
-            .add_system(update_power_ups.in_set(OnUpdate(GameState::Playing)))
-            .add_system(update_health_bar.in_set(OnUpdate(GameState::Playing)));
+        app.add_system(
+            setup_ui
+                .after(seed_run)
+                .in_schedule(OnEnter(GameState::Init)),
+        )
+        .add_system(setup_touch_controls.in_schedule(OnEnter(GameState::Init)))
+        .add_system(despawn_ui.in_schedule(OnEnter(GameState::Restart)))
+        .add_system(update_touch_controls)
+        .add_system(update_score.in_set(OnUpdate(GameState::Playing)))
+        .add_system(update_power_ups.in_set(OnUpdate(GameState::Playing)))
+        .add_system(update_health_bar.in_set(OnUpdate(GameState::Playing)))
+        .add_system(update_ghost_gap.in_set(OnUpdate(GameState::Playing)))
+        .add_system(update_speed.in_set(OnUpdate(GameState::Playing)));
     }
 }
 
@@ -33,13 +48,25 @@ struct ScoreText;
 #[derive(Component)]
 struct TimeText;
 
+#[derive(Component)]
+struct SpeedText;
+
 #[derive(Component)]
 struct HealthBar;
 
 #[derive(Component)]
 struct PowerUpWrapper;
 
-fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
+#[derive(Component)]
+struct GhostText;
+
+#[derive(Component)]
+struct TouchControlsWrapper;
+
+const TOUCH_BUTTON_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.5);
+const TOUCH_BUTTON_PRESSED_COLOR: Color = Color::rgba(0.6, 0.6, 0.6, 0.7);
+
+fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>, challenge: Res<DailyChallenge>) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -146,6 +173,16 @@ fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                             ..default()
                         })
                         .with_children(|parent| {
+                            if let Some(day) = challenge.0 {
+                                parent.spawn(TextBundle::from_section(
+                                    format!("Daily challenge {}", format_date(day * 86_400)),
+                                    TextStyle {
+                                        font: font_assets.fira_mono.clone(),
+                                        font_size: 18.0,
+                                        color: Color::rgb(0.1, 0.1, 0.1),
+                                    },
+                                ));
+                            }
                             parent
                                 .spawn(NodeBundle {
                                     style: Style {
@@ -172,6 +209,16 @@ fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                                         })
                                         .insert(HealthBar);
                                 });
+                            parent
+                                .spawn(TextBundle::from_section(
+                                    "",
+                                    TextStyle {
+                                        font: font_assets.fira_mono.clone(),
+                                        font_size: 18.0,
+                                        color: Color::rgb(0.1, 0.1, 0.1),
+                                    },
+                                ))
+                                .insert(GhostText);
                         });
                     parent
                         .spawn(NodeBundle {
@@ -207,13 +254,162 @@ fn setup_ui(mut commands: Commands, font_assets: Res<FontAssets>) {
                                     },
                                 ))
                                 .insert(TimeText);
+                            parent.spawn(TextBundle::from_section(
+                                "Speed",
+                                TextStyle {
+                                    font: font_assets.fira_mono.clone(),
+                                    font_size: 18.0,
+                                    color: Color::rgb(0.1, 0.1, 0.1),
+                                },
+                            ));
+                            parent
+                                .spawn(TextBundle::from_section(
+                                    "",
+                                    TextStyle {
+                                        font: font_assets.fira_mono.clone(),
+                                        font_size: 20.0,
+                                        color: Color::rgb(0.1, 0.1, 0.1),
+                                    },
+                                ))
+                                .insert(SpeedText);
                         });
                 });
         });
 }
 
+/// Steering and throttle buttons in the bottom corners, below the rest of the HUD, with pause in between.
+/// They stay hidden until the screen gets touched
+fn setup_touch_controls(mut commands: Commands, font_assets: Res<FontAssets>) {
+    let row = || NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::End,
+            ..default()
+        },
+        ..default()
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::width(Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    left: Val::Px(0.),
+                    bottom: Val::Px(0.),
+                    ..default()
+                },
+                flex_direction: FlexDirection::Row,
+                justify_content: JustifyContent::SpaceBetween,
+                align_items: AlignItems::End,
+                padding: UiRect::all(Val::Px(10.)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(TouchControlsWrapper)
+        .with_children(|parent| {
+            parent.spawn(row()).with_children(|parent| {
+                spawn_touch_button(parent, &font_assets, TouchButton::SteerLeft);
+                spawn_touch_button(parent, &font_assets, TouchButton::SteerRight);
+            });
+            spawn_touch_button(parent, &font_assets, TouchButton::Pause);
+            parent.spawn(row()).with_children(|parent| {
+                spawn_touch_button(parent, &font_assets, TouchButton::SpreadShot);
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            flex_direction: FlexDirection::Column,
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        spawn_touch_button(parent, &font_assets, TouchButton::SpeedUp);
+                        spawn_touch_button(parent, &font_assets, TouchButton::SlowDown);
+                    });
+            });
+        });
+}
+
+fn spawn_touch_button(parent: &mut ChildBuilder, font_assets: &FontAssets, button: TouchButton) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: TOUCH_BUTTON_COLOR.into(),
+            ..default()
+        })
+        .insert(button)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                button.label(),
+                TextStyle {
+                    font: font_assets.fira_sans_reg.clone(),
+                    font_size: 18.0,
+                    color: Color::rgb(0.9, 0.9, 0.9),
+                },
+            ));
+        });
+}
+
+/// Shows the touch controls while playing once the screen got touched, highlights the pressed buttons
+/// and scales the buttons with the window so they stay big enough for a thumb on small screens
+fn update_touch_controls(
+    touch_controls: Res<TouchControls>,
+    state: Res<State<GameState>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    mut wrapper_q: Query<&mut Visibility, With<TouchControlsWrapper>>,
+    mut buttons_q: Query<(&TouchButton, &mut Style, &mut BackgroundColor)>,
+) {
+    let Ok(mut visibility) = wrapper_q.get_single_mut() else {
+        return;
+    };
+    let shown = touch_controls.enabled && state.0 == GameState::Playing;
+    let wanted_visibility = if shown {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if *visibility != wanted_visibility {
+        *visibility = wanted_visibility;
+    }
+    if !shown {
+        return;
+    }
+
+    let Ok(window) = window_q.get_single() else {
+        return;
+    };
+    let button_size = (window.width().min(window.height()) * 0.14).clamp(56., 110.);
+    for (button, mut style, mut color) in buttons_q.iter_mut() {
+        let size = if *button == TouchButton::Pause {
+            Size::new(Val::Px(button_size), Val::Px(button_size / 2.))
+        } else {
+            Size::new(Val::Px(button_size), Val::Px(button_size))
+        };
+        // Only touch the style when it changes, the whole UI is laid out again otherwise
+        if style.size != size {
+            style.size = size;
+            style.margin = UiRect::all(Val::Px(button_size / 12.));
+        }
+        let wanted_color = if touch_controls.pressed(*button) {
+            TOUCH_BUTTON_PRESSED_COLOR
+        } else {
+            TOUCH_BUTTON_COLOR
+        };
+        if color.0 != wanted_color {
+            color.0 = wanted_color;
+        }
+    }
+}
+
 fn update_score(
     game_score: Res<GameScore>,
+    balance: Res<Balance>,
     mut text_q: ParamSet<(
         Query<&mut Text, With<TimeText>>,
         Query<&mut Text, With<ScoreText>>,
@@ -228,21 +424,41 @@ fn update_score(
 
     let mut st = text_q.p1();
     let score_text = &mut st.get_single_mut().unwrap();
-    score_text.sections[0].value = format!(
-        "{}",
-        game_score.score + game_score.distance_traveled as i32 / 50
-    );
+    score_text.sections[0].value = format!("{}", game_score.final_score(&balance.difficulty));
 
     let mut dt = text_q.p2();
     let distance_text = &mut dt.get_single_mut().unwrap();
     distance_text.sections[0].value = format!("{:.0}m", game_score.distance_traveled);
 }
 
+fn update_speed(
+    player_q: Query<&Movement, (With<Player>, Changed<Movement>)>,
+    mut speed_text_q: Query<&mut Text, With<SpeedText>>,
+) {
+    let Ok(movement) = player_q.get_single() else {
+        return;
+    };
+    let mut speed_text = speed_text_q.get_single_mut().unwrap();
+    // 16 pixels make a meter, a knot is about half a meter per second
+    speed_text.sections[0].value = format!("{:.0}kn", movement.speed / 16. * 1.944);
+}
+
+fn update_ghost_gap(gap: Res<GhostGap>, mut ghost_text_q: Query<&mut Text, With<GhostText>>) {
+    let mut ghost_text = ghost_text_q.get_single_mut().unwrap();
+    ghost_text.sections[0].value = match gap.0 {
+        Some(gap) if gap >= 0. => format!("Best run {:.0}m behind", gap),
+        Some(gap) => format!("Best run {:.0}m ahead", -gap),
+        None => String::new(),
+    };
+}
+
 fn update_health_bar(
-    health_q: Query<&Health, With<Player>>,
+    health_q: Query<&Health, (With<Player>, Changed<Health>)>,
     mut health_bar_q: Query<&mut Style, With<HealthBar>>,
 ) {
-    let health = health_q.get_single().unwrap();
+    let Ok(health) = health_q.get_single() else {
+        return;
+    };
     let mut health_bar = health_bar_q.get_single_mut().unwrap();
 
     health_bar.size = Size::width(Val::Percent(
@@ -262,9 +478,7 @@ fn update_power_ups(
     } else {
         0
     };
-    if timers.weapon.len() == children_len {
-        return;
-    } else if timers.weapon.len() > children_len {
+    if timers.weapon.len() > children_len {
         commands
             .spawn(ImageBundle {
                 image: UiImage::new(textures.power_up_weapon.clone()),
@@ -277,13 +491,17 @@ fn update_power_ups(
                 ..default()
             })
             .set_parent(wrapper);
-    } else {
+    } else if timers.weapon.len() < children_len {
         let children = children.unwrap()[0];
         commands.entity(children).despawn_recursive();
     }
 }
 
-fn despawn_ui(mut commands: Commands, ui_q: Query<Entity, With<UiWrapper>>) {
-    let ui_entity = ui_q.get_single().unwrap();
-    commands.entity(ui_entity).despawn_recursive();
+fn despawn_ui(
+    mut commands: Commands,
+    ui_q: Query<Entity, Or<(With<UiWrapper>, With<TouchControlsWrapper>)>>,
+) {
+    for ui_entity in ui_q.iter() {
+        commands.entity(ui_entity).despawn_recursive();
+    }
 }